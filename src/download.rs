@@ -0,0 +1,296 @@
+//! Downloading works in the formats AO3 offers
+//!
+//! Work pages link to generated files at `/downloads/{id}/{title}.{ext}`.
+//! The links carry an `updated_at` parameter, so they are read from the work
+//! page instead of being built by hand. Files are fetched through
+//! [`get_page`] and share the client's rate limit and retry policy.
+use crate::errors::Ao3ApiError;
+use crate::networking::config::DEFAULT_BASE_URL;
+use crate::networking::{Ao3Client, get_page, get_work_page};
+use crate::utils::{join_url, make_selector, percent_decode, safe_static_selector};
+use crate::{define_selector, make_static};
+use reqwest::Url;
+use reqwest::header::CONTENT_TYPE;
+use scraper::{Html, selector::Selector};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+define_selector!(
+    DOWNLOAD_LINK_SELECTOR,
+    DOWNLOAD_LINK_SELECTOR_TEXT,
+    r#"li.download a[href*="/downloads/"]"#
+);
+
+/// File formats AO3 generates for every work
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DownloadFormat {
+    Azw3,
+    Epub,
+    Mobi,
+    Pdf,
+    Html,
+}
+
+impl DownloadFormat {
+    /// Every format, in the order AO3 lists them
+    pub const ALL: [DownloadFormat; 5] = [
+        DownloadFormat::Azw3,
+        DownloadFormat::Epub,
+        DownloadFormat::Mobi,
+        DownloadFormat::Pdf,
+        DownloadFormat::Html,
+    ];
+
+    /// File extension without the dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DownloadFormat::Azw3 => "azw3",
+            DownloadFormat::Epub => "epub",
+            DownloadFormat::Mobi => "mobi",
+            DownloadFormat::Pdf => "pdf",
+            DownloadFormat::Html => "html",
+        }
+    }
+}
+
+impl fmt::Display for DownloadFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.extension().to_uppercase())
+    }
+}
+
+impl FromStr for DownloadFormat {
+    type Err = Ao3ApiError;
+
+    /// Parse an extension or AO3's label for it, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().trim_start_matches('.');
+        DownloadFormat::ALL
+            .into_iter()
+            .find(|format| format.extension().eq_ignore_ascii_case(s))
+            .ok_or_else(|| Ao3ApiError::GenericError(format!("Unknown download format {}", s)))
+    }
+}
+
+/// A download link found on a work page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadLink {
+    pub format: DownloadFormat,
+    pub url: String,
+}
+
+impl DownloadLink {
+    /// File name AO3 gives the download, made safe to use on any filesystem
+    ///
+    /// AO3 already strips most punctuation from titles, this decodes the
+    /// percent-encoded path and replaces whatever is still unsafe with `_`.
+    pub fn filename(&self) -> String {
+        let name = Url::parse(&self.url)
+            .ok()
+            .and_then(|url| url.path_segments()?.next_back().map(percent_decode))
+            .unwrap_or_default();
+        let name: String = name
+            .chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            })
+            .collect();
+        let name = name.trim_matches(|c: char| c == '.' || c.is_whitespace());
+        if name.is_empty() {
+            format!("work.{}", self.format.extension())
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+/// What was downloaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Download {
+    pub format: DownloadFormat,
+    pub filename: String,
+    /// Where the file was written, `None` when streamed to a writer
+    pub path: Option<PathBuf>,
+    /// Content type reported by AO3
+    pub content_type: Option<String>,
+    /// Bytes written
+    pub size: u64,
+}
+
+/// Find the download links on a work page
+///
+/// # Arguments
+/// * `document` - the parsed work page
+///
+/// # Returns
+/// * returns one link per format offered, works that are still being processed may have none
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::download::extract_download_links;
+/// use scraper::Html;
+/// let document = Html::parse_document("<li class=\"download\">...</li>");
+/// for link in extract_download_links(&document).unwrap_or_default() {
+///     println!("{}: {}", link.format, link.url);
+/// }
+/// ```
+#[inline(always)]
+pub fn extract_download_links(document: &Html) -> Result<Vec<DownloadLink>, Ao3ApiError> {
+    extract_download_links_with_base(document, DEFAULT_BASE_URL)
+}
+
+/// Find the download links on a work page served by a mirror or proxy
+///
+/// # Arguments
+/// * `document` - the parsed work page
+/// * `base_url` - host the page came from, the links are resolved against it
+pub fn extract_download_links_with_base(
+    document: &Html,
+    base_url: &str,
+) -> Result<Vec<DownloadLink>, Ao3ApiError> {
+    let mut links: Vec<DownloadLink> = Vec::new();
+    for link in document.select(&safe_static_selector(
+        DOWNLOAD_LINK_SELECTOR.clone(),
+        DOWNLOAD_LINK_SELECTOR_TEXT,
+    )?) {
+        let Some(href) = link.attr("href") else {
+            continue;
+        };
+        let url = join_url(base_url, href);
+        // the extension is more reliable than the link text
+        let format = Url::parse(&url).ok().and_then(|url| {
+            let name = url.path_segments()?.next_back()?.to_string();
+            name.rsplit_once('.')?.1.parse::<DownloadFormat>().ok()
+        });
+        if let Some(format) = format
+            && !links.iter().any(|known| known.format == format)
+        {
+            links.push(DownloadLink { format, url });
+        }
+    }
+    Ok(links)
+}
+
+/// Get the download links of a work
+///
+/// # Arguments
+/// * `work_url` - URL of the work, or a path like `/works/123`
+/// * `client` - client to use
+pub fn download_links(
+    work_url: &str,
+    client: &Ao3Client,
+) -> Result<Vec<DownloadLink>, Ao3ApiError> {
+    let document = get_work_page(work_url, client)?;
+    extract_download_links_with_base(&document, &client.config().base_url)
+}
+
+/// Stream a download into `writer`
+///
+/// # Arguments
+/// * `link` - a link from [`extract_download_links`] or [`download_links`]
+/// * `client` - client to use
+/// * `writer` - where the file goes
+///
+/// # Returns
+/// * Returns what was downloaded, `path` is always `None`
+pub fn download_to_writer<W: Write + ?Sized>(
+    link: &DownloadLink,
+    client: &Ao3Client,
+    writer: &mut W,
+) -> Result<Download, Ao3ApiError> {
+    let page = get_page(&link.url, client)?;
+    let content_type = page
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let size = page.copy_to(writer)?;
+    writer.flush()?;
+    Ok(Download {
+        format: link.format,
+        filename: link.filename(),
+        path: None,
+        content_type,
+        size,
+    })
+}
+
+/// Save a download into `dir` under the name AO3 gives it
+///
+/// A file of that name is only replaced once the download succeeded.
+///
+/// # Arguments
+/// * `link` - a link from [`extract_download_links`] or [`download_links`]
+/// * `client` - client to use
+/// * `dir` - directory to save into, it must already exist
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::download::{DownloadFormat, download_links, download_to_file};
+/// use ao3_api_rs::networking::create_client;
+/// let client = create_client("test").unwrap();
+/// let links = download_links("/works/123456", &client).unwrap();
+/// if let Some(epub) = links.iter().find(|link| link.format == DownloadFormat::Epub) {
+///     let download = download_to_file(epub, &client, ".").unwrap();
+///     println!("saved {} bytes to {:?}", download.size, download.path);
+/// }
+/// ```
+pub fn download_to_file(
+    link: &DownloadLink,
+    client: &Ao3Client,
+    dir: impl AsRef<Path>,
+) -> Result<Download, Ao3ApiError> {
+    let path = dir.as_ref().join(link.filename());
+    // download next to the target and rename it into place, a failed
+    // download then leaves an earlier copy of the file alone
+    let partial = dir
+        .as_ref()
+        .join(format!(".{}.{}.part", link.filename(), std::process::id()));
+    let mut writer = BufWriter::new(File::create(&partial)?);
+    let download = download_to_writer(link, client, &mut writer);
+    drop(writer);
+    let download = download.and_then(|download| {
+        fs::rename(&partial, &path)?;
+        Ok(download)
+    });
+    match download {
+        Ok(download) => Ok(Download {
+            path: Some(path),
+            ..download
+        }),
+        Err(err) => {
+            // don't leave a truncated file behind
+            let _ = fs::remove_file(&partial);
+            Err(err)
+        }
+    }
+}
+
+/// Download a work in `format` into `dir`
+///
+/// # Arguments
+/// * `work_url` - URL of the work, or a path like `/works/123`
+/// * `format` - format to download
+/// * `client` - client to use
+/// * `dir` - directory to save into
+pub fn download_work(
+    work_url: &str,
+    format: DownloadFormat,
+    client: &Ao3Client,
+    dir: impl AsRef<Path>,
+) -> Result<Download, Ao3ApiError> {
+    let link = download_links(work_url, client)?
+        .into_iter()
+        .find(|link| link.format == format)
+        .ok_or_else(|| Ao3ApiError::Parse {
+            what: format!("{} download link", format),
+            url: client.config().url(work_url),
+        })?;
+    download_to_file(&link, client, dir)
+}
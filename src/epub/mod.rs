@@ -0,0 +1,443 @@
+//! EPUB 3 output for extracted works
+//!
+//! Builds an EPUB from [`FicMetadata`] and the [`Chapter`]s of a work: a title
+//! page listing the metadata as it was when the book was written, one XHTML
+//! document per chapter and a navigation document. Unlike AO3's own EPUBs the
+//! chapter bodies are the sanitized HTML from [`extract_chapters`], so
+//! formatting AO3's converter drops is kept.
+//!
+//! [`extract_chapters`]: crate::extraction::extract_chapters
+use crate::errors::Ao3ApiError;
+use crate::extraction::{Chapter, WorkPage};
+use crate::utils::{escape_html, sanitize_html_epub};
+use ficdata::FicMetadata;
+use scraper::Html;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::ZipWriter;
+
+mod zip;
+
+static CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+static STYLESHEET: &str = r#"body { margin: 0 5%; line-height: 1.4; }
+h1, h2 { text-align: center; }
+p.byline { text-align: center; font-style: italic; }
+dl.meta dt { font-weight: bold; margin-top: 0.5em; }
+dl.meta dd { margin-left: 1.5em; }
+section.notes, section.summary { margin: 1em 0; padding: 0 1em; border-left: 2px solid #999; }
+span.big { font-size: larger; }
+"#;
+
+/// Tag categories shown on the title page, in AO3's order
+static TAG_LABELS: [(&str, &str); 7] = [
+    ("ratings", "Rating"),
+    ("warnings", "Archive Warnings"),
+    ("categories", "Categories"),
+    ("fandoms", "Fandoms"),
+    ("relationships", "Relationships"),
+    ("characters", "Characters"),
+    ("freeforms", "Additional Tags"),
+];
+
+/// Language names AO3 displays and their BCP 47 codes
+static LANGUAGE_CODES: [(&str, &str); 22] = [
+    ("English", "en"),
+    ("Español", "es"),
+    ("Français", "fr"),
+    ("Deutsch", "de"),
+    ("Italiano", "it"),
+    ("Português brasileiro", "pt-BR"),
+    ("Português europeu", "pt-PT"),
+    ("Русский", "ru"),
+    ("中文-普通话 國語", "zh"),
+    ("日本語", "ja"),
+    ("한국어", "ko"),
+    ("Polski", "pl"),
+    ("Nederlands", "nl"),
+    ("Bahasa Indonesia", "id"),
+    ("Tiếng Việt", "vi"),
+    ("Türkçe", "tr"),
+    ("Svenska", "sv"),
+    ("Suomi", "fi"),
+    ("Dansk", "da"),
+    ("Norsk", "no"),
+    ("Čeština", "cs"),
+    ("Українська", "uk"),
+];
+
+/// Writes a work as an EPUB 3 file
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::epub::EpubWriter;
+/// use ao3_api_rs::extraction::{extract_chapters, extract_work_page};
+/// use ao3_api_rs::networking::{create_client, get_work_page};
+/// let client = create_client("test").unwrap();
+/// let document = get_work_page("/works/123456?view_full_work=true", &client).unwrap();
+/// let work = extract_work_page(&document).unwrap();
+/// let chapters = extract_chapters(&document).unwrap();
+/// EpubWriter::from_work(work, chapters)
+///     .save("work.epub")
+///     .expect("Failed to write epub");
+/// ```
+#[derive(Debug, Clone)]
+pub struct EpubWriter {
+    metadata: FicMetadata,
+    chapters: Vec<Chapter>,
+    /// Extra title page rows, e.g. publication date and bookmarks from a work page
+    details: Vec<(&'static str, String)>,
+    language: Option<String>,
+    modified: SystemTime,
+}
+
+impl EpubWriter {
+    pub fn new(metadata: FicMetadata, chapters: Vec<Chapter>) -> Self {
+        Self {
+            metadata,
+            chapters,
+            details: Vec::new(),
+            language: None,
+            modified: SystemTime::now(),
+        }
+    }
+
+    /// Use a work page's metadata, adding its dates, comments and bookmarks to the title page
+    pub fn from_work(work: WorkPage, chapters: Vec<Chapter>) -> Self {
+        let details = [
+            ("Published", work.published),
+            ("Updated", work.updated),
+            ("Completed", work.completed),
+            ("Comments", work.comments.map(|n| n.to_string())),
+            ("Bookmarks", work.bookmarks.map(|n| n.to_string())),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| Some((label, value?)))
+        .collect();
+        Self {
+            details,
+            ..Self::new(work.metadata, chapters)
+        }
+    }
+
+    /// Set the BCP 47 language code, by default it is derived from the metadata's language
+    pub fn with_language(mut self, code: &str) -> Self {
+        self.language = Some(code.to_string());
+        self
+    }
+
+    /// Set the modification time recorded in the book, defaults to now
+    pub fn with_modified(mut self, modified: SystemTime) -> Self {
+        self.modified = modified;
+        self
+    }
+
+    /// Write the EPUB to `writer`
+    ///
+    /// # Returns
+    /// * Returns the writer once the archive is complete
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<W, Ao3ApiError> {
+        let language = self.language_code();
+        let mut zip = ZipWriter::new(writer);
+        // the mimetype has to come first and uncompressed
+        zip.add("mimetype", b"application/epub+zip")?;
+        zip.add("META-INF/container.xml", CONTAINER_XML.as_bytes())?;
+        zip.add("OEBPS/content.opf", self.package(&language).as_bytes())?;
+        zip.add("OEBPS/nav.xhtml", self.nav(&language).as_bytes())?;
+        zip.add("OEBPS/style.css", STYLESHEET.as_bytes())?;
+        zip.add("OEBPS/title.xhtml", self.title_page(&language).as_bytes())?;
+        for (index, chapter) in self.chapters.iter().enumerate() {
+            zip.add(
+                &format!("OEBPS/{}", chapter_file(index)),
+                self.chapter_page(chapter, &language).as_bytes(),
+            )?;
+        }
+        Ok(zip.finish()?)
+    }
+
+    /// Write the EPUB to a file at `path`
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Ao3ApiError> {
+        self.write_to(BufWriter::new(File::create(path)?))?
+            .flush()?;
+        Ok(())
+    }
+
+    /// Helper function to get the language code for `dc:language`
+    fn language_code(&self) -> String {
+        if let Some(code) = &self.language {
+            return code.clone();
+        }
+        let Some(name) = self.metadata.language.as_deref().map(str::trim) else {
+            return "und".to_string();
+        };
+        LANGUAGE_CODES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|(_, code)| code.to_string())
+            // the language may already be a code, e.g. from the lang attribute
+            .or_else(|| {
+                (name.len() <= 3 && name.chars().all(|c| c.is_ascii_alphabetic()))
+                    .then(|| name.to_ascii_lowercase())
+            })
+            .unwrap_or_else(|| "und".to_string())
+    }
+
+    /// Title of `chapter` as shown in the table of contents
+    fn chapter_title(&self, chapter: &Chapter) -> String {
+        match &chapter.title {
+            Some(title) => format!("Chapter {}: {}", chapter.number, title),
+            None if self.chapters.len() == 1 => self.metadata.name.clone(),
+            None => format!("Chapter {}", chapter.number),
+        }
+    }
+
+    /// The OPF package document
+    fn package(&self, language: &str) -> String {
+        let meta = &self.metadata;
+        let mut metadata = format!(
+            "    <dc:identifier id=\"pub-id\">{}</dc:identifier>\n    <dc:title>{}</dc:title>\n    <dc:language>{}</dc:language>\n",
+            escape_html(&meta.url),
+            escape_html(&meta.name),
+            escape_html(language)
+        );
+        for (index, author) in meta.authors.iter().enumerate() {
+            metadata.push_str(&format!(
+                "    <dc:creator id=\"creator-{}\">{}</dc:creator>\n    <meta refines=\"#creator-{}\" property=\"role\" scheme=\"marc:relators\">aut</meta>\n",
+                index + 1,
+                escape_html(author),
+                index + 1
+            ));
+        }
+        for fandom in &meta.fandom {
+            metadata.push_str(&format!(
+                "    <dc:subject>{}</dc:subject>\n",
+                escape_html(fandom)
+            ));
+        }
+        if !meta.description.trim().is_empty() {
+            metadata.push_str(&format!(
+                "    <dc:description>{}</dc:description>\n",
+                escape_html(meta.description.trim())
+            ));
+        }
+        metadata.push_str(&format!(
+            "    <dc:publisher>Archive of Our Own</dc:publisher>\n    <dc:source>{}</dc:source>\n    <meta property=\"dcterms:modified\">{}</meta>\n",
+            escape_html(&meta.url),
+            iso_timestamp(self.modified)
+        ));
+
+        let mut manifest = String::from(
+            "    <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n    <item id=\"css\" href=\"style.css\" media-type=\"text/css\"/>\n    <item id=\"title\" href=\"title.xhtml\" media-type=\"application/xhtml+xml\"/>\n",
+        );
+        let mut spine = String::from("    <itemref idref=\"title\"/>\n");
+        for index in 0..self.chapters.len() {
+            manifest.push_str(&format!(
+                "    <item id=\"chapter-{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+                index + 1,
+                chapter_file(index)
+            ));
+            spine.push_str(&format!("    <itemref idref=\"chapter-{}\"/>\n", index + 1));
+        }
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"pub-id\" xml:lang=\"{}\">\n  <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n{}  </metadata>\n  <manifest>\n{}  </manifest>\n  <spine>\n{}  </spine>\n</package>\n",
+            escape_html(language),
+            metadata,
+            manifest,
+            spine
+        )
+    }
+
+    /// The navigation document with the table of contents
+    fn nav(&self, language: &str) -> String {
+        let mut items = String::from("      <li><a href=\"title.xhtml\">Title Page</a></li>\n");
+        for (index, chapter) in self.chapters.iter().enumerate() {
+            items.push_str(&format!(
+                "      <li><a href=\"{}\">{}</a></li>\n",
+                chapter_file(index),
+                escape_html(&self.chapter_title(chapter))
+            ));
+        }
+        let body = format!(
+            "  <nav epub:type=\"toc\" id=\"toc\">\n    <h1>Contents</h1>\n    <ol>\n{}    </ol>\n  </nav>",
+            items
+        );
+        xhtml_page(&self.metadata.name, language, &body)
+    }
+
+    /// The title page listing the work's metadata
+    fn title_page(&self, language: &str) -> String {
+        let meta = &self.metadata;
+        let mut rows: Vec<(String, String)> = Vec::new();
+        for (key, label) in TAG_LABELS {
+            let values = match meta.tags.get(key) {
+                Some(values) if !values.is_empty() => values.clone(),
+                _ if key == "fandoms" => meta.fandom.clone(),
+                _ => Vec::new(),
+            };
+            if !values.is_empty() {
+                rows.push((label.to_string(), values.join(", ")));
+            }
+        }
+        // categories AO3 adds later still show up
+        let mut other: Vec<&String> = meta
+            .tags
+            .keys()
+            .filter(|key| !TAG_LABELS.iter().any(|(known, _)| known == key))
+            .collect();
+        other.sort();
+        for key in other {
+            if !meta.tags[key].is_empty() {
+                rows.push((key.clone(), meta.tags[key].join(", ")));
+            }
+        }
+        if let Some(language) = &meta.language {
+            rows.push(("Language".to_string(), language.clone()));
+        }
+        if !meta.series.is_empty() {
+            rows.push(("Series".to_string(), meta.series.join("; ")));
+        }
+        let stats = [
+            ("Words", meta.words.map(|n| n.to_string())),
+            ("Chapters", meta.chapters.clone()),
+            ("Kudos", meta.kudos.map(|n| n.to_string())),
+            ("Hits", meta.hits.map(|n| n.to_string())),
+        ];
+        for (label, value) in stats {
+            if let Some(value) = value {
+                rows.push((label.to_string(), value));
+            }
+        }
+        for (label, value) in &self.details {
+            rows.push((label.to_string(), value.clone()));
+        }
+        rows.push(("Last Updated".to_string(), meta.last_updated.clone()));
+        rows.push((
+            "Downloaded".to_string(),
+            iso_timestamp(self.modified)[..10].to_string(),
+        ));
+
+        let mut body = format!("  <h1>{}</h1>\n", escape_html(&meta.name));
+        if !meta.authors.is_empty() {
+            body.push_str(&format!(
+                "  <p class=\"byline\">by {}</p>\n",
+                escape_html(&meta.authors.join(", "))
+            ));
+        }
+        body.push_str("  <dl class=\"meta\">\n");
+        for (label, value) in rows {
+            body.push_str(&format!(
+                "    <dt>{}</dt>\n    <dd>{}</dd>\n",
+                escape_html(&label),
+                escape_html(&value)
+            ));
+        }
+        body.push_str("  </dl>\n");
+        if !meta.description.trim().is_empty() {
+            body.push_str(&format!(
+                "  <section class=\"summary\">\n    <h2>Summary</h2>\n{}  </section>\n",
+                text_paragraphs(&meta.description)
+            ));
+        }
+        body.push_str(&format!(
+            "  <p>Source: <a href=\"{}\">{}</a></p>",
+            escape_html(&meta.url),
+            escape_html(&meta.url)
+        ));
+        xhtml_page(&meta.name, language, &body)
+    }
+
+    /// One chapter with its summary and notes
+    fn chapter_page(&self, chapter: &Chapter, language: &str) -> String {
+        let title = self.chapter_title(chapter);
+        let mut body = format!("  <h2>{}</h2>\n", escape_html(&title));
+        let sections = [
+            ("summary", "Summary", &chapter.summary),
+            ("notes", "Notes", &chapter.notes),
+        ];
+        for (class, heading, text) in sections {
+            if let Some(text) = text {
+                body.push_str(&format!(
+                    "  <section class=\"{}\">\n    <h3>{}</h3>\n{}  </section>\n",
+                    class,
+                    heading,
+                    text_paragraphs(text)
+                ));
+            }
+        }
+        // EPUB 3 allows neither remote images nor presentational tags, so both are rewritten
+        let html = sanitize_html_epub(Html::parse_fragment(&chapter.html).root_element());
+        body.push_str(&format!("  <div class=\"chapter\">\n{}\n  </div>\n", html));
+        if let Some(text) = &chapter.end_notes {
+            body.push_str(&format!(
+                "  <section class=\"notes\">\n    <h3>End Notes</h3>\n{}  </section>\n",
+                text_paragraphs(text)
+            ));
+        }
+        xhtml_page(&title, language, body.trim_end())
+    }
+}
+
+/// File name of chapter `index` inside the book
+fn chapter_file(index: usize) -> String {
+    format!("chapter-{}.xhtml", index + 1)
+}
+
+/// Helper function to wrap `body` in an XHTML document
+fn xhtml_page(title: &str, language: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" xml:lang=\"{}\" lang=\"{}\">\n<head>\n  <meta charset=\"UTF-8\"/>\n  <title>{}</title>\n  <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+        escape_html(language),
+        escape_html(language),
+        escape_html(title),
+        body
+    )
+}
+
+/// Helper function to turn plain text with blank line separated paragraphs into `<p>`s
+fn text_paragraphs(text: &str) -> String {
+    text.split("\n\n")
+        .map(str::trim)
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| {
+            let lines: Vec<String> = paragraph.lines().map(escape_html).collect();
+            format!("    <p>{}</p>\n", lines.join("<br/>"))
+        })
+        .collect()
+}
+
+/// Format `time` as `CCYY-MM-DDThh:mm:ssZ` for `dcterms:modified`
+fn iso_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or(0);
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+    // days since the epoch to a civil date, Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
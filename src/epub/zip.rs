@@ -0,0 +1,118 @@
+//! Minimal zip writer for EPUB containers
+//!
+//! EPUB readers accept uncompressed entries and the `mimetype` entry has to be
+//! stored uncompressed anyway, so this writes every entry with method 0
+//! (stored) instead of pulling in a compression library.
+use std::io::{self, Write};
+
+/// Central directory record for an entry that was already written
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Writes stored (uncompressed) zip entries to `W` in order
+pub(crate) struct ZipWriter<W: Write> {
+    writer: W,
+    entries: Vec<Entry>,
+    offset: u32,
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub(crate) fn new(writer: W) -> Self {
+        Self {
+            writer,
+            entries: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    /// Write one file, entries end up in the archive in the order they are added
+    pub(crate) fn add(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let size = u32::try_from(data.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "zip entry over 4 GiB"))?;
+        let crc = crc32(data);
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        header.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        header.extend_from_slice(&0x0800u16.to_le_bytes()); // utf-8 names
+        header.extend_from_slice(&0u16.to_le_bytes()); // stored
+        header.extend_from_slice(&0u16.to_le_bytes()); // time 00:00
+        header.extend_from_slice(&0x0021u16.to_le_bytes()); // date 1980-01-01
+        header.extend_from_slice(&crc.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+        header.extend_from_slice(name.as_bytes());
+        self.writer.write_all(&header)?;
+        self.writer.write_all(data)?;
+
+        self.entries.push(Entry {
+            name: name.to_string(),
+            crc,
+            size,
+            offset: self.offset,
+        });
+        self.offset = self
+            .offset
+            .checked_add(header.len() as u32)
+            .and_then(|offset| offset.checked_add(size))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "zip over 4 GiB"))?;
+        Ok(())
+    }
+
+    /// Write the central directory and return the inner writer
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        let start = self.offset;
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            directory.extend_from_slice(&0x02014b50u32.to_le_bytes());
+            directory.extend_from_slice(&20u16.to_le_bytes()); // version made by
+            directory.extend_from_slice(&20u16.to_le_bytes()); // version needed
+            directory.extend_from_slice(&0x0800u16.to_le_bytes());
+            directory.extend_from_slice(&0u16.to_le_bytes());
+            directory.extend_from_slice(&0u16.to_le_bytes());
+            directory.extend_from_slice(&0x0021u16.to_le_bytes());
+            directory.extend_from_slice(&entry.crc.to_le_bytes());
+            directory.extend_from_slice(&entry.size.to_le_bytes());
+            directory.extend_from_slice(&entry.size.to_le_bytes());
+            directory.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+            directory.extend_from_slice(&0u16.to_le_bytes()); // comment length
+            directory.extend_from_slice(&0u16.to_le_bytes()); // disk number
+            directory.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+            directory.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+            directory.extend_from_slice(&entry.offset.to_le_bytes());
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+        let count = self.entries.len() as u16;
+        let size = directory.len() as u32;
+        directory.extend_from_slice(&0x06054b50u32.to_le_bytes());
+        directory.extend_from_slice(&0u16.to_le_bytes()); // this disk
+        directory.extend_from_slice(&0u16.to_le_bytes()); // disk with the directory
+        directory.extend_from_slice(&count.to_le_bytes());
+        directory.extend_from_slice(&count.to_le_bytes());
+        directory.extend_from_slice(&size.to_le_bytes());
+        directory.extend_from_slice(&start.to_le_bytes());
+        directory.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        self.writer.write_all(&directory)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// CRC-32 (IEEE) as used by zip
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    !crc
+}
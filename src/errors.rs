@@ -1,3 +1,6 @@
+use crate::extraction::Gate;
+use reqwest::StatusCode;
+use std::time::Duration;
 use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum Ao3ApiError {
@@ -11,4 +14,27 @@ pub enum Ao3ApiError {
     IoError(#[from] std::io::Error),
     #[error("{0}")]
     GenericError(String),
+    /// AO3 answered with a status we don't know how to handle
+    #[error("http error: {status} from {url}")]
+    Http { status: StatusCode, url: String },
+    /// The request never got a response (DNS, connection, timeout, ...)
+    #[error("network error: {0}")]
+    Network(#[from] reqwest::Error),
+    /// Following redirects led back to a URL we already visited, or took too many hops
+    #[error("redirect loop: {}", .0.join(" -> "))]
+    RedirectLoop(Vec<String>),
+    /// AO3 redirected to the login page, the work is restricted to logged in users
+    #[error("login required to view {url}")]
+    LoginRequired { url: String },
+    /// AO3 kept rate limiting us
+    #[error("rate limited, retry after {retry_after:?}")]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("authentication failed: {0}")]
+    AuthFailed(String),
+    /// AO3 showed an interstitial or error page instead of the work
+    #[error("work unavailable: {0}")]
+    Gated(Gate),
+    /// A response didn't contain what we expected
+    #[error("failed to parse {what} from {url}")]
+    Parse { what: String, url: String },
 }
@@ -0,0 +1,403 @@
+//! Extraction for bookmark blurbs (`/users/{name}/bookmarks`, `/bookmarks?...`)
+//!
+//! A bookmark blurb is the blurb of the bookmarked item followed by a
+//! `div.user.module` with the bookmarker's name, date, notes, tags and
+//! collections. The bookmarker's tags use the same markup as the work's, so
+//! that module is cut out before the item is handed to the blurb extractors.
+use super::user::extract_series_blurb;
+use super::{
+    Pseud, SeriesBlurb, UserWork, extract_creators_with_base, extract_fic_metadata_with_base,
+    extract_tags,
+};
+use crate::errors::Ao3ApiError;
+use crate::networking::config::DEFAULT_BASE_URL;
+use crate::utils::{html_to_text, html_without, join_url, make_selector, safe_static_selector};
+use crate::{define_selector, make_static, select_raw_text, select_text};
+use ficdata::TagMap;
+use scraper::{ElementRef, Html, selector::Selector};
+use std::sync::LazyLock;
+
+define_selector!(
+    BOOKMARK_BLURB_SELECTOR,
+    BOOKMARK_BLURB_SELECTOR_TEXT,
+    r#"li.bookmark.blurb"#
+);
+define_selector!(
+    BOOKMARKER_MODULE_SELECTOR,
+    BOOKMARKER_MODULE_SELECTOR_TEXT,
+    r#"div.user.module"#
+);
+define_selector!(
+    ITEM_LINK_SELECTOR,
+    ITEM_LINK_SELECTOR_TEXT,
+    r#"h4.heading a"#
+);
+define_selector!(
+    ITEM_HEADING_SELECTOR,
+    ITEM_HEADING_SELECTOR_TEXT,
+    r#"h4.heading"#
+);
+define_selector!(
+    ITEM_FANDOM_SELECTOR,
+    ITEM_FANDOM_SELECTOR_TEXT,
+    r#"h5.fandoms a.tag"#
+);
+define_selector!(
+    ITEM_SUMMARY_SELECTOR,
+    ITEM_SUMMARY_SELECTOR_TEXT,
+    r#"blockquote.userstuff.summary"#
+);
+define_selector!(DATETIME_SELECTOR, DATETIME_SELECTOR_TEXT, r#"p.datetime"#);
+define_selector!(
+    BOOKMARKER_SELECTOR,
+    BOOKMARKER_SELECTOR_TEXT,
+    r#"h5.byline a[href*="/users/"]"#
+);
+define_selector!(
+    BOOKMARK_TAG_SELECTOR,
+    BOOKMARK_TAG_SELECTOR_TEXT,
+    r#"ul.meta.tags a.tag"#
+);
+define_selector!(
+    BOOKMARK_COLLECTION_SELECTOR,
+    BOOKMARK_COLLECTION_SELECTOR_TEXT,
+    r#"a[href*="/collections/"]"#
+);
+define_selector!(
+    BOOKMARK_NOTES_SELECTOR,
+    BOOKMARK_NOTES_SELECTOR_TEXT,
+    r#"blockquote.userstuff.notes"#
+);
+define_selector!(REC_SELECTOR, REC_SELECTOR_TEXT, r#"p.status span.rec"#);
+define_selector!(
+    PRIVATE_SELECTOR,
+    PRIVATE_SELECTOR_TEXT,
+    r#"p.status span.private"#
+);
+
+/// An external work, a story hosted elsewhere that was bookmarked on AO3
+#[derive(Debug, Clone)]
+pub struct ExternalWork {
+    pub id: String,
+    pub title: String,
+    /// AO3's page for the external work, it links to the story itself
+    pub url: String,
+    /// Creator names as entered by the bookmarker, they aren't AO3 accounts
+    pub creators: Vec<String>,
+    pub fandoms: Vec<String>,
+    pub tags: TagMap,
+    pub summary: Option<String>,
+}
+
+/// What a bookmark points to
+#[derive(Debug, Clone)]
+pub enum BookmarkedItem {
+    Work(UserWork),
+    Series(SeriesBlurb),
+    External(ExternalWork),
+    /// A deleted or hidden item, with the message AO3 shows in its place,
+    /// e.g. "This has been deleted, sorry!"
+    Unavailable(Option<String>),
+}
+
+/// The bookmarker's part of a bookmark
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BookmarkRecord {
+    /// Bookmark id, from the blurb's `bookmark_{id}` element id
+    pub id: Option<String>,
+    pub bookmarker: Option<Pseud>,
+    /// Date the bookmark was made, e.g. "20 Feb 2024"
+    pub date: Option<String>,
+    pub notes: Option<String>,
+    /// The bookmarker's own tags, separate from the item's tags
+    pub tags: Vec<String>,
+    pub collections: Vec<String>,
+    /// Marked as a recommendation
+    pub rec: bool,
+    /// Only visible to the bookmarker
+    pub private: bool,
+}
+
+/// A bookmark blurb, split into the bookmarked item and the bookmark itself
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+    pub item: BookmarkedItem,
+    pub record: BookmarkRecord,
+}
+
+/// Helper function to get a blurb's HTML without the bookmarker module
+///
+/// Work blurbs pass through unchanged.
+pub(crate) fn strip_bookmarker(blurb: ElementRef<'_>) -> Result<String, Ao3ApiError> {
+    Ok(html_without(
+        blurb,
+        &safe_static_selector(
+            BOOKMARKER_MODULE_SELECTOR.clone(),
+            BOOKMARKER_MODULE_SELECTOR_TEXT,
+        )?,
+    ))
+}
+
+/// Helper function to get the trimmed text of the first match of `selector` in `element`
+fn first_text(
+    element: ElementRef<'_>,
+    selector: &Option<Selector>,
+    backup: &str,
+) -> Result<Option<String>, Ao3ApiError> {
+    Ok(element
+        .select(&safe_static_selector(selector.clone(), backup)?)
+        .next()
+        .map(|elem| elem.text().collect::<String>().trim().to_string())
+        .filter(|text| !text.is_empty()))
+}
+
+/// Helper function to parse the bookmarker module of a blurb
+fn extract_record(blurb: ElementRef<'_>, base_url: &str) -> Result<BookmarkRecord, Ao3ApiError> {
+    let id = blurb
+        .value()
+        .id()
+        .and_then(|id| id.strip_prefix("bookmark_"))
+        .map(str::to_string);
+    let Some(module) = blurb
+        .select(&safe_static_selector(
+            BOOKMARKER_MODULE_SELECTOR.clone(),
+            BOOKMARKER_MODULE_SELECTOR_TEXT,
+        )?)
+        .next()
+    else {
+        return Ok(BookmarkRecord {
+            id,
+            ..BookmarkRecord::default()
+        });
+    };
+
+    let has = |selector: &Option<Selector>, backup: &str| -> Result<bool, Ao3ApiError> {
+        Ok(module
+            .select(&safe_static_selector(selector.clone(), backup)?)
+            .next()
+            .is_some())
+    };
+
+    Ok(BookmarkRecord {
+        id,
+        bookmarker: module
+            .select(&safe_static_selector(
+                BOOKMARKER_SELECTOR.clone(),
+                BOOKMARKER_SELECTOR_TEXT,
+            )?)
+            .filter_map(|link| link.attr("href"))
+            .find_map(|href| Pseud::from_href(href, base_url)),
+        date: first_text(module, &DATETIME_SELECTOR, DATETIME_SELECTOR_TEXT)?,
+        notes: module
+            .select(&safe_static_selector(
+                BOOKMARK_NOTES_SELECTOR.clone(),
+                BOOKMARK_NOTES_SELECTOR_TEXT,
+            )?)
+            .next()
+            .map(html_to_text)
+            .filter(|notes| !notes.is_empty()),
+        tags: select_text!(
+            module,
+            &safe_static_selector(BOOKMARK_TAG_SELECTOR.clone(), BOOKMARK_TAG_SELECTOR_TEXT)?
+        ),
+        collections: select_text!(
+            module,
+            &safe_static_selector(
+                BOOKMARK_COLLECTION_SELECTOR.clone(),
+                BOOKMARK_COLLECTION_SELECTOR_TEXT
+            )?
+        ),
+        rec: has(&REC_SELECTOR, REC_SELECTOR_TEXT)?,
+        private: has(&PRIVATE_SELECTOR, PRIVATE_SELECTOR_TEXT)?,
+    })
+}
+
+/// Helper function to parse an external work from a blurb without its bookmarker module
+fn extract_external_work(
+    item: &str,
+    title: &str,
+    href: &str,
+    base_url: &str,
+) -> Result<ExternalWork, Ao3ApiError> {
+    let fragment = Html::parse_fragment(item);
+    let root = fragment.root_element();
+    let heading = root
+        .select(&safe_static_selector(
+            ITEM_HEADING_SELECTOR.clone(),
+            ITEM_HEADING_SELECTOR_TEXT,
+        )?)
+        .next()
+        .map(|elem| elem.text().collect::<String>())
+        .unwrap_or_default();
+    let heading = heading.split_whitespace().collect::<Vec<_>>().join(" ");
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    // "Title by Someone, Someone Else", the creators aren't linked
+    let creators = heading
+        .strip_prefix(title.as_str())
+        .unwrap_or_default()
+        .trim_start()
+        .strip_prefix("by")
+        .unwrap_or_default();
+
+    Ok(ExternalWork {
+        id: href
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+        title,
+        url: join_url(base_url, href),
+        creators: creators
+            .split(", ")
+            .map(str::trim)
+            .filter(|creator| !creator.is_empty())
+            .map(str::to_string)
+            .collect(),
+        fandoms: select_text!(
+            root,
+            &safe_static_selector(ITEM_FANDOM_SELECTOR.clone(), ITEM_FANDOM_SELECTOR_TEXT)?
+        ),
+        tags: extract_tags(&fragment).unwrap_or_default(),
+        summary: root
+            .select(&safe_static_selector(
+                ITEM_SUMMARY_SELECTOR.clone(),
+                ITEM_SUMMARY_SELECTOR_TEXT,
+            )?)
+            .next()
+            .map(html_to_text)
+            .filter(|summary| !summary.is_empty()),
+    })
+}
+
+/// Helper function to parse one bookmark blurb
+fn parse_bookmark(blurb: ElementRef<'_>, base_url: &str) -> Result<Bookmark, Ao3ApiError> {
+    let item = strip_bookmarker(blurb)?;
+    let record = extract_record(blurb, base_url)?;
+    let Some((link, href)) = blurb
+        .select(&safe_static_selector(
+            ITEM_LINK_SELECTOR.clone(),
+            ITEM_LINK_SELECTOR_TEXT,
+        )?)
+        .filter_map(|link| Some((link, link.attr("href")?)))
+        .find(|(_, href)| {
+            href.contains("/works/")
+                || href.contains("/series/")
+                || href.contains("/external_works/")
+        })
+    else {
+        // deleted and hidden items leave only a message behind
+        let message = Html::parse_fragment(&item)
+            .root_element()
+            .text()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        return Ok(Bookmark {
+            item: BookmarkedItem::Unavailable(Some(message).filter(|text| !text.is_empty())),
+            record,
+        });
+    };
+
+    let item = if href.contains("/external_works/") {
+        let title = link.text().collect::<String>();
+        BookmarkedItem::External(extract_external_work(&item, &title, href, base_url)?)
+    } else if href.contains("/series/") {
+        let fragment = Html::parse_fragment(&item);
+        BookmarkedItem::Series(extract_series_blurb(fragment.root_element(), base_url)?)
+    } else {
+        BookmarkedItem::Work(UserWork {
+            metadata: extract_fic_metadata_with_base(&item, base_url)?,
+            creators: extract_creators_with_base(&Html::parse_fragment(&item), base_url)?,
+        })
+    };
+
+    Ok(Bookmark { item, record })
+}
+
+/// Extract a single bookmark blurb
+///
+/// # Arguments
+/// * `item` - the html of the bookmark's `li.bookmark.blurb`
+///
+/// # Returns
+/// * returns the bookmarked work, series or external work and the bookmark record,
+///   bookmarks of deleted works come back as [`BookmarkedItem::Unavailable`]
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::extraction::{BookmarkedItem, extract_bookmark};
+/// let html = "<li class=\"bookmark blurb group\">...</li>";
+/// if let Ok(bookmark) = extract_bookmark(html) {
+///     if let BookmarkedItem::Work(work) = bookmark.item {
+///         println!("{} tagged {:?}", work.metadata.name, bookmark.record.tags);
+///     }
+/// }
+/// ```
+#[inline(always)]
+pub fn extract_bookmark(item: &str) -> Result<Bookmark, Ao3ApiError> {
+    extract_bookmark_with_base(item, DEFAULT_BASE_URL)
+}
+
+/// Extract a single bookmark blurb served by a mirror or proxy
+///
+/// # Arguments
+/// * `item` - the html of the bookmark's `li.bookmark.blurb`
+/// * `base_url` - host the page came from, links are resolved against it
+pub fn extract_bookmark_with_base(item: &str, base_url: &str) -> Result<Bookmark, Ao3ApiError> {
+    let fragment = Html::parse_fragment(item);
+    let blurb = fragment
+        .select(&safe_static_selector(
+            BOOKMARK_BLURB_SELECTOR.clone(),
+            BOOKMARK_BLURB_SELECTOR_TEXT,
+        )?)
+        .next()
+        .unwrap_or(fragment.root_element());
+    parse_bookmark(blurb, base_url)
+}
+
+/// Extract every bookmark blurb on a bookmarks page
+///
+/// # Arguments
+/// * `document` - a parsed bookmarks page, e.g. `/users/name/bookmarks` or a bookmark search
+///
+/// # Returns
+/// * returns one result per blurb so a single odd blurb doesn't lose the whole page
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::extraction::extract_bookmarks;
+/// use scraper::Html;
+/// let document = Html::parse_document("<ol class=\"bookmark index group\">...</ol>");
+/// let recs: Vec<_> = extract_bookmarks(&document)
+///     .into_iter()
+///     .flatten()
+///     .filter(|bookmark| bookmark.record.rec)
+///     .collect();
+/// ```
+#[inline(always)]
+pub fn extract_bookmarks(document: &Html) -> Vec<Result<Bookmark, Ao3ApiError>> {
+    extract_bookmarks_with_base(document, DEFAULT_BASE_URL)
+}
+
+/// Extract every bookmark blurb on a bookmarks page served by a mirror or proxy
+///
+/// # Arguments
+/// * `document` - a parsed bookmarks page
+/// * `base_url` - host the page came from, links are resolved against it
+pub fn extract_bookmarks_with_base(
+    document: &Html,
+    base_url: &str,
+) -> Vec<Result<Bookmark, Ao3ApiError>> {
+    match safe_static_selector(
+        BOOKMARK_BLURB_SELECTOR.clone(),
+        BOOKMARK_BLURB_SELECTOR_TEXT,
+    ) {
+        Ok(selector) => document
+            .select(&selector)
+            .map(|blurb| parse_bookmark(blurb, base_url))
+            .collect(),
+        Err(err) => vec![Err(err)],
+    }
+}
@@ -0,0 +1,228 @@
+//! Extraction of chapter text from work pages
+//!
+//! Works with both a single chapter (`/works/{id}/chapters/{chapter_id}`) and
+//! the whole work (`/works/{id}?view_full_work=true`). One-shots have no
+//! chapter markup at all and come back as a single untitled chapter.
+use crate::errors::Ao3ApiError;
+use crate::utils::{
+    html_to_text, make_selector, safe_static_regex, safe_static_selector, sanitize_html,
+};
+use crate::{define_regex, define_selector, make_static};
+use regex::Regex;
+use scraper::{ElementRef, Html, selector::Selector};
+use std::sync::LazyLock;
+
+define_regex!(
+    CHAPTER_ID_REGEX,
+    CHAPTER_ID_REGEX_TEXT,
+    r#"/chapters/(\d+)"#
+);
+define_regex!(
+    CHAPTER_NUM_REGEX,
+    CHAPTER_NUM_REGEX_TEXT,
+    r#"^chapter-(\d+)$"#
+);
+define_selector!(
+    CHAPTER_SELECTOR,
+    CHAPTER_SELECTOR_TEXT,
+    r#"#chapters > div.chapter"#
+);
+define_selector!(
+    ONESHOT_SELECTOR,
+    ONESHOT_SELECTOR_TEXT,
+    r#"#chapters > div.userstuff"#
+);
+define_selector!(
+    CHAPTER_TITLE_SELECTOR,
+    CHAPTER_TITLE_SELECTOR_TEXT,
+    r#"div.chapter.preface h3.title"#
+);
+define_selector!(LINK_SELECTOR, LINK_SELECTOR_TEXT, "a");
+define_selector!(
+    CHAPTER_SUMMARY_SELECTOR,
+    CHAPTER_SUMMARY_SELECTOR_TEXT,
+    r#"div.chapter.preface div.summary blockquote.userstuff"#
+);
+define_selector!(
+    CHAPTER_NOTES_SELECTOR,
+    CHAPTER_NOTES_SELECTOR_TEXT,
+    r#"div.chapter.preface div.notes:not(.end) blockquote.userstuff"#
+);
+define_selector!(
+    CHAPTER_END_NOTES_SELECTOR,
+    CHAPTER_END_NOTES_SELECTOR_TEXT,
+    r#"div.chapter.preface div.end.notes blockquote.userstuff"#
+);
+define_selector!(
+    CHAPTER_BODY_SELECTOR,
+    CHAPTER_BODY_SELECTOR_TEXT,
+    r#"div.userstuff.module"#
+);
+
+/// A single chapter of a work
+#[derive(Debug, Clone)]
+pub struct Chapter {
+    /// AO3's chapter id, `None` for one-shots which have no chapter pages
+    pub id: Option<String>,
+    /// 1-based position of the chapter in the work
+    pub number: u32,
+    /// Chapter title without the "Chapter N: " prefix, if the chapter has one
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    /// Notes shown before the chapter text
+    pub notes: Option<String>,
+    /// Notes shown after the chapter text
+    pub end_notes: Option<String>,
+    /// Chapter body as sanitized HTML
+    pub html: String,
+    /// Chapter body as plain text, paragraphs separated by blank lines
+    pub text: String,
+}
+
+/// Helper function to get the trimmed text of the first match of `selector` in `element`
+fn first_text(
+    element: ElementRef<'_>,
+    selector: &Option<Selector>,
+    backup: &str,
+) -> Result<Option<String>, Ao3ApiError> {
+    Ok(element
+        .select(&safe_static_selector(selector.clone(), backup)?)
+        .next()
+        .map(html_to_text)
+        .filter(|text| !text.is_empty()))
+}
+
+/// Helper function to extract one `div.chapter`
+fn extract_chapter(element: ElementRef<'_>, index: usize) -> Result<Chapter, Ao3ApiError> {
+    // prefer the number in the element id, single chapter pages are not numbered from 1
+    let number = element
+        .value()
+        .id()
+        .and_then(|id| {
+            safe_static_regex(CHAPTER_NUM_REGEX.clone(), CHAPTER_NUM_REGEX_TEXT)
+                .ok()?
+                .captures(id)?
+                .get(1)?
+                .as_str()
+                .parse()
+                .ok()
+        })
+        .unwrap_or(index as u32 + 1);
+
+    let heading = element
+        .select(&safe_static_selector(
+            CHAPTER_TITLE_SELECTOR.clone(),
+            CHAPTER_TITLE_SELECTOR_TEXT,
+        )?)
+        .next();
+    let id = heading
+        .and_then(|heading| {
+            heading
+                .select(&safe_static_selector(LINK_SELECTOR.clone(), LINK_SELECTOR_TEXT).ok()?)
+                .next()?
+                .attr("href")
+                .map(str::to_string)
+        })
+        .and_then(|href| {
+            safe_static_regex(CHAPTER_ID_REGEX.clone(), CHAPTER_ID_REGEX_TEXT)
+                .ok()?
+                .captures(&href)?
+                .get(1)
+                .map(|id| id.as_str().to_string())
+        });
+    // "Chapter 2: The Title" -> "The Title"
+    let title = heading.and_then(|heading| {
+        let text = heading.text().collect::<String>();
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        text.split_once(':')
+            .map(|(_, title)| title.trim().to_string())
+            .filter(|title| !title.is_empty())
+    });
+
+    let body = element
+        .select(&safe_static_selector(
+            CHAPTER_BODY_SELECTOR.clone(),
+            CHAPTER_BODY_SELECTOR_TEXT,
+        )?)
+        .next()
+        .ok_or(Ao3ApiError::SelectorError(format!(
+            "Failed to select body of chapter {}",
+            number
+        )))?;
+
+    Ok(Chapter {
+        id,
+        number,
+        title,
+        summary: first_text(
+            element,
+            &CHAPTER_SUMMARY_SELECTOR,
+            CHAPTER_SUMMARY_SELECTOR_TEXT,
+        )?,
+        notes: first_text(
+            element,
+            &CHAPTER_NOTES_SELECTOR,
+            CHAPTER_NOTES_SELECTOR_TEXT,
+        )?,
+        end_notes: first_text(
+            element,
+            &CHAPTER_END_NOTES_SELECTOR,
+            CHAPTER_END_NOTES_SELECTOR_TEXT,
+        )?,
+        html: sanitize_html(body),
+        text: html_to_text(body),
+    })
+}
+
+/// Extract the chapters of a work page in reading order
+///
+/// # Arguments
+/// * `document` - the parsed work page, as returned by `get_init_page`
+///
+/// # Returns
+/// * returns the chapters in order, a one-shot yields a single chapter
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::extraction::extract_chapters;
+/// use scraper::Html;
+/// let document = Html::parse_document("<div id=\"chapters\">...</div>");
+/// for chapter in extract_chapters(&document).unwrap_or_default() {
+///     println!("{}: {:?}", chapter.number, chapter.title);
+/// }
+/// ```
+pub fn extract_chapters(document: &Html) -> Result<Vec<Chapter>, Ao3ApiError> {
+    let chapters = document
+        .select(&safe_static_selector(
+            CHAPTER_SELECTOR.clone(),
+            CHAPTER_SELECTOR_TEXT,
+        )?)
+        .enumerate()
+        .map(|(index, element)| extract_chapter(element, index))
+        .collect::<Result<Vec<Chapter>, Ao3ApiError>>()?;
+    if !chapters.is_empty() {
+        return Ok(chapters);
+    }
+
+    // one-shots put the text straight into #chapters
+    let body = document
+        .select(&safe_static_selector(
+            ONESHOT_SELECTOR.clone(),
+            ONESHOT_SELECTOR_TEXT,
+        )?)
+        .next()
+        .ok_or(Ao3ApiError::SelectorError(
+            "Failed to find any chapters in #chapters".to_string(),
+        ))?;
+
+    Ok(vec![Chapter {
+        id: None,
+        number: 1,
+        title: None,
+        summary: None,
+        notes: None,
+        end_notes: None,
+        html: sanitize_html(body),
+        text: html_to_text(body),
+    }])
+}
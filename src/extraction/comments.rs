@@ -0,0 +1,366 @@
+//! Extraction of comment threads
+//!
+//! Comments are shown on `/works/{id}/comments`, on `/chapters/{id}?show_comments=true`
+//! and, one thread at a time, on `/comments/{id}`. Every page nests them the
+//! same way: an `ol.thread` of `li.comment`s, where the replies to a comment
+//! sit in an `ol.thread` inside the plain `li` that follows it. Deep threads
+//! are cut off with a link to the thread's own page, kept in
+//! [`Comment::thread_url`] so the replies can be fetched later.
+use super::Pseud;
+use crate::errors::Ao3ApiError;
+use crate::networking::config::DEFAULT_BASE_URL;
+use crate::utils::{
+    html_to_text, join_url, make_selector, safe_static_regex, safe_static_selector, sanitize_html,
+};
+use crate::{define_regex, define_selector, make_static};
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, selector::Selector};
+use std::sync::LazyLock;
+
+define_regex!(
+    COMMENT_CHAPTER_REGEX,
+    COMMENT_CHAPTER_REGEX_TEXT,
+    r#"Chapter\s+(\d+)"#
+);
+define_selector!(THREAD_SELECTOR, THREAD_SELECTOR_TEXT, r#"ol.thread"#);
+define_selector!(
+    COMMENT_HEADING_SELECTOR,
+    COMMENT_HEADING_SELECTOR_TEXT,
+    r#"h4.heading.byline"#
+);
+define_selector!(
+    COMMENT_AUTHOR_SELECTOR,
+    COMMENT_AUTHOR_SELECTOR_TEXT,
+    r#"a[href*="/users/"]"#
+);
+define_selector!(
+    COMMENT_PARENT_SELECTOR,
+    COMMENT_PARENT_SELECTOR_TEXT,
+    r#"span.parent"#
+);
+define_selector!(
+    COMMENT_POSTED_SELECTOR,
+    COMMENT_POSTED_SELECTOR_TEXT,
+    r#"span.posted.datetime"#
+);
+define_selector!(
+    COMMENT_BODY_SELECTOR,
+    COMMENT_BODY_SELECTOR_TEXT,
+    r#"blockquote.userstuff"#
+);
+define_selector!(
+    COMMENT_EDITED_SELECTOR,
+    COMMENT_EDITED_SELECTOR_TEXT,
+    r#"p.edited"#
+);
+define_selector!(
+    THREAD_LINK_SELECTOR,
+    THREAD_LINK_SELECTOR_TEXT,
+    r#"a[href*="/comments/"]"#
+);
+
+/// Who wrote a comment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommentAuthor {
+    User(Pseud),
+    /// Name a logged out commenter gave
+    Guest(String),
+}
+
+/// A comment and its replies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub id: String,
+    /// `None` for deleted comments
+    pub author: Option<CommentAuthor>,
+    /// When the comment was posted as AO3 shows it, e.g. "Tue 20 Feb 2024 09:15PM UTC"
+    pub posted: Option<String>,
+    /// Chapter the comment is on, only shown for works with several chapters
+    pub chapter: Option<u32>,
+    /// Comment body as sanitized HTML
+    pub html: String,
+    /// Comment body as plain text, paragraphs separated by blank lines
+    pub text: String,
+    /// Id of the comment this replies to
+    pub parent_id: Option<String>,
+    pub edited: bool,
+    pub replies: Vec<Comment>,
+    /// Page with the rest of the thread when AO3 didn't show all replies
+    pub thread_url: Option<String>,
+}
+
+impl Comment {
+    /// Number of comments in this thread, this one included
+    pub fn thread_len(&self) -> usize {
+        1 + self.replies.iter().map(Comment::thread_len).sum::<usize>()
+    }
+
+    /// Find a comment by id in this thread
+    pub fn find(&self, id: &str) -> Option<&Comment> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.replies.iter().find_map(|reply| reply.find(id))
+    }
+}
+
+/// Selectors and settings shared while walking a thread
+struct ThreadParser {
+    thread: Selector,
+    heading: Selector,
+    author: Selector,
+    parent: Selector,
+    posted: Selector,
+    body: Selector,
+    edited: Selector,
+    thread_link: Selector,
+    chapter: Regex,
+    base_url: String,
+}
+
+impl ThreadParser {
+    fn new(base_url: &str) -> Result<Self, Ao3ApiError> {
+        Ok(Self {
+            thread: safe_static_selector(THREAD_SELECTOR.clone(), THREAD_SELECTOR_TEXT)?,
+            heading: safe_static_selector(
+                COMMENT_HEADING_SELECTOR.clone(),
+                COMMENT_HEADING_SELECTOR_TEXT,
+            )?,
+            author: safe_static_selector(
+                COMMENT_AUTHOR_SELECTOR.clone(),
+                COMMENT_AUTHOR_SELECTOR_TEXT,
+            )?,
+            parent: safe_static_selector(
+                COMMENT_PARENT_SELECTOR.clone(),
+                COMMENT_PARENT_SELECTOR_TEXT,
+            )?,
+            posted: safe_static_selector(
+                COMMENT_POSTED_SELECTOR.clone(),
+                COMMENT_POSTED_SELECTOR_TEXT,
+            )?,
+            body: safe_static_selector(COMMENT_BODY_SELECTOR.clone(), COMMENT_BODY_SELECTOR_TEXT)?,
+            edited: safe_static_selector(
+                COMMENT_EDITED_SELECTOR.clone(),
+                COMMENT_EDITED_SELECTOR_TEXT,
+            )?,
+            thread_link: safe_static_selector(
+                THREAD_LINK_SELECTOR.clone(),
+                THREAD_LINK_SELECTOR_TEXT,
+            )?,
+            chapter: safe_static_regex(COMMENT_CHAPTER_REGEX.clone(), COMMENT_CHAPTER_REGEX_TEXT)?,
+            base_url: base_url.to_string(),
+        })
+    }
+
+    /// Parse the comments of `thread`, replying to `parent_id`
+    ///
+    /// Also returns a thread link that isn't preceded by a comment, it belongs
+    /// to the parent, whose replies were cut off entirely.
+    fn parse_thread(
+        &self,
+        thread: ElementRef<'_>,
+        parent_id: Option<&str>,
+    ) -> (Vec<Comment>, Option<String>) {
+        let mut comments: Vec<Comment> = Vec::new();
+        let mut dangling = None;
+        for item in thread.children().filter_map(ElementRef::wrap) {
+            if item.value().name() != "li" {
+                continue;
+            }
+            if item
+                .value()
+                .has_class("comment", scraper::CaseSensitivity::CaseSensitive)
+            {
+                comments.push(self.parse_comment(item, parent_id));
+                continue;
+            }
+
+            // a plain li holds the replies to the comment before it, or a link to them
+            let nested = item
+                .children()
+                .filter_map(ElementRef::wrap)
+                .find(|child| self.thread.matches(child));
+            let (replies, link) = match nested {
+                Some(nested) => {
+                    let id = comments.last().map(|comment| comment.id.clone());
+                    self.parse_thread(nested, id.as_deref().or(parent_id))
+                }
+                None => (Vec::new(), self.thread_link(item)),
+            };
+            match comments.last_mut() {
+                Some(comment) => {
+                    comment.replies.extend(replies);
+                    if link.is_some() {
+                        comment.thread_url = link;
+                    }
+                }
+                None => {
+                    comments.extend(replies);
+                    dangling = dangling.or(link);
+                }
+            }
+        }
+        (comments, dangling)
+    }
+
+    /// Helper function to get the link of a collapsed thread
+    fn thread_link(&self, item: ElementRef<'_>) -> Option<String> {
+        item.select(&self.thread_link)
+            .filter_map(|link| link.attr("href"))
+            .next()
+            .map(|href| join_url(&self.base_url, href))
+    }
+
+    fn parse_comment(&self, item: ElementRef<'_>, parent_id: Option<&str>) -> Comment {
+        let id = item
+            .value()
+            .id()
+            .map(|id| id.trim_start_matches("comment_").to_string())
+            .unwrap_or_default();
+        let heading = item.select(&self.heading).next();
+
+        let author = heading.and_then(|heading| {
+            if let Some(pseud) = heading
+                .select(&self.author)
+                .filter_map(|link| link.attr("href"))
+                .find_map(|href| Pseud::from_href(href, &self.base_url))
+            {
+                return Some(CommentAuthor::User(pseud));
+            }
+            // guests are plain text in front of the chapter and date spans
+            let name: String = heading
+                .children()
+                .filter_map(|child| match child.value() {
+                    Node::Text(text) => Some(text.to_string()),
+                    _ => None,
+                })
+                .collect();
+            let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+            let name = name.trim_end_matches("(Guest)").trim();
+            (!name.is_empty()).then(|| CommentAuthor::Guest(name.to_string()))
+        });
+
+        let text_of = |selector: &Selector| {
+            heading
+                .and_then(|heading| heading.select(selector).next())
+                .map(|elem| {
+                    elem.text()
+                        .collect::<String>()
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+        };
+        let chapter = text_of(&self.parent).and_then(|parent| {
+            self.chapter
+                .captures(&parent)
+                .and_then(|cap| cap.get(1))
+                .and_then(|number| number.as_str().parse().ok())
+        });
+
+        let body = item.select(&self.body).next();
+        Comment {
+            id,
+            author,
+            posted: text_of(&self.posted),
+            chapter,
+            html: body.map(sanitize_html).unwrap_or_default(),
+            text: body.map(html_to_text).unwrap_or_default(),
+            parent_id: parent_id.map(str::to_string),
+            edited: item.select(&self.edited).next().is_some(),
+            replies: Vec::new(),
+            thread_url: None,
+        }
+    }
+}
+
+/// Extract the comment threads on a page
+///
+/// # Arguments
+/// * `document` - a parsed comments page, e.g. `/works/123/comments`,
+///   `/chapters/456?show_comments=true` or `/comments/789`
+///
+/// # Returns
+/// * returns the top level comments on the page with their replies nested below them
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::extraction::extract_comments;
+/// use scraper::Html;
+/// let document = Html::parse_document("<ol class=\"thread\">...</ol>");
+/// for comment in extract_comments(&document).unwrap_or_default() {
+///     println!("{}: {} replies", comment.id, comment.replies.len());
+/// }
+/// ```
+#[inline(always)]
+pub fn extract_comments(document: &Html) -> Result<Vec<Comment>, Ao3ApiError> {
+    extract_comments_with_base(document, DEFAULT_BASE_URL)
+}
+
+/// Extract the comment threads on a page served by a mirror or proxy
+///
+/// # Arguments
+/// * `document` - a parsed comments page
+/// * `base_url` - host the page came from, links are resolved against it
+pub fn extract_comments_with_base(
+    document: &Html,
+    base_url: &str,
+) -> Result<Vec<Comment>, Ao3ApiError> {
+    let parser = ThreadParser::new(base_url)?;
+    let mut comments = Vec::new();
+    for thread in document.select(&parser.thread) {
+        // nested threads are handled while walking their parent
+        let is_nested = thread
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .any(|ancestor| parser.thread.matches(&ancestor));
+        if !is_nested {
+            comments.extend(parser.parse_thread(thread, None).0);
+        }
+    }
+    Ok(comments)
+}
+
+/// Helper function to list the collapsed threads in `comments`
+///
+/// # Returns
+/// * returns the index path to each comment with a thread link, and the link
+pub(crate) fn collapsed_threads(comments: &[Comment]) -> Vec<(Vec<usize>, String)> {
+    let mut found = Vec::new();
+    for (index, comment) in comments.iter().enumerate() {
+        if let Some(url) = &comment.thread_url {
+            found.push((vec![index], url.clone()));
+        }
+        for (mut path, url) in collapsed_threads(&comment.replies) {
+            path.insert(0, index);
+            found.push((path, url));
+        }
+    }
+    found
+}
+
+/// Helper function to fill in a collapsed thread from its own page
+///
+/// # Arguments
+/// * `comments` - the tree the collapsed comment is in
+/// * `path` - index path from [`collapsed_threads`]
+/// * `thread` - comments extracted from the thread page
+pub(crate) fn merge_thread(comments: &mut [Comment], path: &[usize], thread: &[Comment]) {
+    let Some((&first, rest)) = path.split_first() else {
+        return;
+    };
+    let Some(mut comment) = comments.get_mut(first) else {
+        return;
+    };
+    for &index in rest {
+        let Some(reply) = comment.replies.get_mut(index) else {
+            return;
+        };
+        comment = reply;
+    }
+    // clear the link even when the page doesn't have the comment, so it isn't fetched forever
+    comment.thread_url = None;
+    if let Some(full) = thread.iter().find_map(|root| root.find(&comment.id)) {
+        comment.replies = full.replies.clone();
+    }
+}
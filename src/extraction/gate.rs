@@ -0,0 +1,113 @@
+//! Detection of pages that stand in for a work
+//!
+//! AO3 answers some work requests with an interstitial or error page instead
+//! of the work itself. Spotting these up front gives callers a typed reason
+//! instead of a selector error from an extractor that found nothing.
+use crate::utils::{make_selector, safe_static_selector};
+use crate::{define_selector, make_static};
+use scraper::{Html, selector::Selector};
+use std::fmt;
+use std::sync::LazyLock;
+
+define_selector!(CAUTION_SELECTOR, CAUTION_SELECTOR_TEXT, r#"p.caution"#);
+// only the flash, notice and error containers, headings hold work and series titles
+define_selector!(
+    NOTICE_SELECTOR,
+    NOTICE_SELECTOR_TEXT,
+    r#"div.flash, p.notice, div.notice, div.error, #main.errors"#
+);
+define_selector!(
+    LOGIN_FORM_SELECTOR,
+    LOGIN_FORM_SELECTOR_TEXT,
+    r#"#main form#new_user"#
+);
+
+/// Why a work page couldn't be shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gate {
+    /// "This work could have adult content", needs `view_adult=true`
+    AdultContent,
+    /// Archive-locked work, only visible to logged in users
+    LoginRequired,
+    /// Hidden by an administrator
+    HiddenByAdmin,
+    /// The work was deleted
+    Deleted,
+    /// Nothing exists at this URL
+    NotFound,
+}
+
+impl fmt::Display for Gate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            Gate::AdultContent => "adult content warning",
+            Gate::LoginRequired => "login required",
+            Gate::HiddenByAdmin => "hidden by an administrator",
+            Gate::Deleted => "deleted",
+            Gate::NotFound => "not found",
+        };
+        f.write_str(reason)
+    }
+}
+
+/// Detect whether `document` is a gate page instead of a work
+///
+/// # Arguments
+/// * `document` - a parsed page fetched for a work
+///
+/// # Returns
+/// * returns the kind of gate, or `None` for a regular page
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::extraction::{Gate, detect_gate};
+/// use scraper::Html;
+/// let document = Html::parse_document("<p class=\"caution\">This work could have adult content.</p>");
+/// assert_eq!(detect_gate(&document), Some(Gate::AdultContent));
+/// ```
+pub fn detect_gate(document: &Html) -> Option<Gate> {
+    let select_any = |selector: &Option<Selector>, backup: &str| {
+        safe_static_selector(selector.clone(), backup)
+            .is_ok_and(|selector| document.select(&selector).next().is_some())
+    };
+
+    let caution = safe_static_selector(CAUTION_SELECTOR.clone(), CAUTION_SELECTOR_TEXT)
+        .ok()
+        .and_then(|selector| {
+            document
+                .select(&selector)
+                .next()
+                .map(|elem| elem.text().collect::<String>())
+        });
+    // accepted works link to view_adult=true too, so only trust the caution text
+    if caution.is_some_and(|text| text.contains("adult content")) {
+        return Some(Gate::AdultContent);
+    }
+
+    let notices = safe_static_selector(NOTICE_SELECTOR.clone(), NOTICE_SELECTOR_TEXT)
+        .map(|selector| {
+            document
+                .select(&selector)
+                .map(|elem| elem.text().collect::<String>())
+                .collect::<Vec<String>>()
+                .join(" ")
+        })
+        .unwrap_or_default();
+    if notices.contains("hidden by an administrator") || notices.contains("has been hidden") {
+        return Some(Gate::HiddenByAdmin);
+    }
+    if notices.contains("has been deleted") || notices.contains("couldn't find the work") {
+        return Some(Gate::Deleted);
+    }
+    if notices.contains("Error 404") || notices.contains("page you were looking for doesn't exist")
+    {
+        return Some(Gate::NotFound);
+    }
+    if notices.contains("only available to registered users")
+        || select_any(&LOGIN_FORM_SELECTOR, LOGIN_FORM_SELECTOR_TEXT)
+    {
+        return Some(Gate::LoginRequired);
+    }
+
+    None
+}
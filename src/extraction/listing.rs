@@ -0,0 +1,154 @@
+//! Extraction for listing pages
+//!
+//! Search results, tag works, bookmarks, collections and user works pages all
+//! share the same layout: a heading with the result count, an `ol` of blurbs
+//! and an `ol.pagination` bar.
+use super::bookmarks::strip_bookmarker;
+use super::{extract_fic_metadata_with_base, parse_number_with_commas};
+use crate::errors::Ao3ApiError;
+use crate::networking::config::DEFAULT_BASE_URL;
+use crate::utils::{make_selector, safe_static_regex, safe_static_selector};
+use crate::{define_regex, define_selector, make_static};
+use ficdata::FicMetadata;
+use regex::Regex;
+use scraper::{Html, selector::Selector};
+use std::sync::LazyLock;
+
+define_selector!(
+    BLURB_SELECTOR,
+    BLURB_SELECTOR_TEXT,
+    r#"li.work.blurb, li.bookmark.blurb"#
+);
+define_selector!(
+    LISTING_HEADING_SELECTOR,
+    LISTING_HEADING_SELECTOR_TEXT,
+    r#"h2.heading, h3.heading"#
+);
+define_selector!(
+    CURRENT_PAGE_SELECTOR,
+    CURRENT_PAGE_SELECTOR_TEXT,
+    r#"ol.pagination span.current"#
+);
+define_selector!(
+    PAGINATION_ITEM_SELECTOR,
+    PAGINATION_ITEM_SELECTOR_TEXT,
+    r#"ol.pagination li"#
+);
+// "1 - 20 of 12,345 Works in Fluff", "35 Works by someone" or "12,345 Found"
+define_regex!(
+    RESULT_COUNT_REGEX,
+    RESULT_COUNT_REGEX_TEXT,
+    r#"(?:([\d,]+)\s*-\s*([\d,]+)\s+of\s+)?([\d,]+)\s+(?:Works|Bookmarks|Bookmarked Items|Series|Gifts|Found)"#
+);
+
+/// Header information of a listing page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListingInfo {
+    /// Position of the first blurb on this page in the whole listing, 1-based
+    pub first: Option<u32>,
+    /// Position of the last blurb on this page in the whole listing
+    pub last: Option<u32>,
+    /// Total number of results in the listing
+    pub total: Option<u32>,
+    pub current_page: u32,
+    pub last_page: u32,
+}
+
+/// Extract every work blurb on a listing page
+///
+/// # Arguments
+/// * `document` - a parsed search, tag, bookmarks, collection or user works page
+///
+/// # Returns
+/// * returns one result per blurb so a single odd blurb doesn't lose the whole page
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::extraction::extract_listing;
+/// use scraper::Html;
+/// let document = Html::parse_document("<ol class=\"work index group\">...</ol>");
+/// let fics: Vec<_> = extract_listing(&document).into_iter().flatten().collect();
+/// ```
+#[inline(always)]
+pub fn extract_listing(document: &Html) -> Vec<Result<FicMetadata, Ao3ApiError>> {
+    extract_listing_with_base(document, DEFAULT_BASE_URL)
+}
+
+/// Extract every work blurb on a listing page served by a mirror or proxy
+///
+/// # Arguments
+/// * `document` - a parsed listing page
+/// * `base_url` - host the page came from, work links are resolved against it
+pub fn extract_listing_with_base(
+    document: &Html,
+    base_url: &str,
+) -> Vec<Result<FicMetadata, Ao3ApiError>> {
+    match safe_static_selector(BLURB_SELECTOR.clone(), BLURB_SELECTOR_TEXT) {
+        Ok(selector) => document
+            .select(&selector)
+            .map(|blurb| {
+                // bookmark blurbs carry the bookmarker's tags, which aren't the work's
+                extract_fic_metadata_with_base(&strip_bookmarker(blurb)?, base_url)
+            })
+            .collect(),
+        Err(err) => vec![Err(err)],
+    }
+}
+
+/// Extract the result count and page position of a listing page
+///
+/// # Arguments
+/// * `document` - a parsed listing page
+///
+/// # Returns
+/// * returns the listing header, pages default to 1 when there is no pagination bar
+pub fn extract_listing_info(document: &Html) -> Result<ListingInfo, Ao3ApiError> {
+    let regex = safe_static_regex(RESULT_COUNT_REGEX.clone(), RESULT_COUNT_REGEX_TEXT)?;
+    let counts = document
+        .select(&safe_static_selector(
+            LISTING_HEADING_SELECTOR.clone(),
+            LISTING_HEADING_SELECTOR_TEXT,
+        )?)
+        .find_map(|heading| {
+            let text = heading.text().collect::<String>();
+            regex.captures(&text).map(|cap| {
+                let number = |i: usize| {
+                    cap.get(i)
+                        .and_then(|m| parse_number_with_commas(m.as_str()).ok())
+                };
+                (number(1), number(2), number(3))
+            })
+        });
+    let (mut first, mut last, total) = counts.unwrap_or_default();
+    // "35 Works by someone" means everything fits on one page
+    if first.is_none() && total.is_some_and(|total| total > 0) {
+        first = Some(1);
+        last = total;
+    }
+
+    let current_page = document
+        .select(&safe_static_selector(
+            CURRENT_PAGE_SELECTOR.clone(),
+            CURRENT_PAGE_SELECTOR_TEXT,
+        )?)
+        .next()
+        .and_then(|elem| elem.text().collect::<String>().trim().parse().ok())
+        .unwrap_or(1);
+    let last_page = document
+        .select(&safe_static_selector(
+            PAGINATION_ITEM_SELECTOR.clone(),
+            PAGINATION_ITEM_SELECTOR_TEXT,
+        )?)
+        .filter_map(|item| item.text().collect::<String>().trim().parse().ok())
+        .max()
+        .unwrap_or(current_page)
+        .max(current_page);
+
+    Ok(ListingInfo {
+        first,
+        last,
+        total,
+        current_page,
+        last_page,
+    })
+}
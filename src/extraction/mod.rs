@@ -15,22 +15,52 @@
 //! }
 //! ```
 use crate::errors::Ao3ApiError;
-use crate::utils::{make_selector, safe_static_regex, safe_static_selector};
+use crate::networking::config::DEFAULT_BASE_URL;
+use crate::utils::{join_url, make_selector, safe_static_regex, safe_static_selector};
 use crate::{
     define_regex, define_selector, make_static, select_raw_text, select_raw_text_next, select_text,
 };
 use ficdata::{FicMetadata, TagMap};
 use regex::Regex;
-use scraper::{Html, selector::Selector};
-use std::collections::HashMap;
+use scraper::{ElementRef, Html, selector::Selector};
 use std::num::ParseIntError;
 use std::sync::LazyLock;
+
+pub mod bookmarks;
+pub mod chapters;
+pub mod comments;
+pub mod gate;
+pub mod listing;
+pub mod series;
+pub mod tags;
+pub mod user;
+pub mod work;
+pub use bookmarks::{
+    Bookmark, BookmarkRecord, BookmarkedItem, ExternalWork, extract_bookmark,
+    extract_bookmark_with_base, extract_bookmarks, extract_bookmarks_with_base,
+};
+pub use chapters::{Chapter, extract_chapters};
+pub use comments::{Comment, CommentAuthor, extract_comments, extract_comments_with_base};
+pub use gate::{Gate, detect_gate};
+pub use listing::{ListingInfo, extract_listing, extract_listing_info, extract_listing_with_base};
+pub use series::{
+    SeriesMembership, SeriesPage, extract_series_memberships, extract_series_memberships_with_base,
+    extract_series_page, extract_series_page_with_base,
+};
+pub use tags::{
+    Tag, TagCategory, TagPage, TagType, extract_tag_page, extract_tag_page_with_base, extract_tags,
+    extract_typed_tags, tag_slug, tags_from_map, tags_to_map,
+};
+pub use user::{
+    Pseud, SeriesBlurb, UserListing, UserProfile, UserWork, extract_creators,
+    extract_creators_with_base, extract_user_bookmarks, extract_user_bookmarks_with_base,
+    extract_user_gifts, extract_user_gifts_with_base, extract_user_profile,
+    extract_user_profile_with_base, extract_user_series, extract_user_series_with_base,
+    extract_user_works, extract_user_works_with_base,
+};
+pub use work::{WorkPage, extract_work_page, extract_work_page_with_base};
+
 // TODO: convert from macros to const functions for better error handling making working on this file less of a mess
-define_regex!(
-    TAG_REGEX,
-    TAG_REGEX_TEXT,
-    r#"<li class="(.*?)">.*?<a class="tag".*?">(.*?)</a"#
-);
 define_regex!(FIC_ID_REGEX, FIC_ID_REGEX_TEXT, r#"/works/(\d+)"#);
 define_selector!(
     HEADING_SELECTOR,
@@ -44,7 +74,6 @@ define_selector!(
     r#"p[class="datetime"]"#
 );
 define_selector!(AUTHOR_SELECTOR, AUTHOR_SELECTOR_TEXT, r#"a[rel="author"]"#);
-define_selector!(SERIES_SELECTOR, SERIES_SELECTOR_TEXT, r#"ul.series li"#);
 define_selector!(
     USER_STUFF_SELECTOR,
     USER_STUFF_SELECTOR_TEXT,
@@ -72,56 +101,60 @@ define_selector!(WORDS_SELECTOR, WORDS_SELECTOR_TEXT, r#"dd.words"#);
 define_selector!(HITS_SELECTOR, HITS_SELECTOR_TEXT, r#"dd.hits"#);
 /// Gets all fic tags from passed in String
 ///
+/// Kept for compatibility, this parses `fic` and calls [`extract_tags`].
+///
 /// # Arguments
 /// * `fic` - a string containing the html of the fic's display area
 ///
 /// # Returns
 /// * returns a HashMap mapping tag categories to vectors of tag values
+#[inline(always)]
 pub fn gettags(fic: String) -> Result<TagMap, Ao3ApiError> {
-    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
-
-    safe_static_regex(TAG_REGEX.clone(), &TAG_REGEX_TEXT)?
-        .captures_iter(&fic)
-        .for_each(|cap| {
-            if let (Some(category), Some(value)) = (cap.get(1), cap.get(2)) {
-                tags.entry(category.as_str().to_string())
-                    .or_default()
-                    .push(value.as_str().to_string());
-            }
-        });
-
-    Ok(tags)
+    extract_tags(&Html::parse_fragment(&fic))
 }
 
 fn parse_number_with_commas(text: &str) -> Result<u32, ParseIntError> {
     text.replace(',', "").parse::<u32>()
 }
 
-/// Helper function to extract series list from HTML document
-fn extract_series_list(document: &Html) -> Result<Vec<String>, Ao3ApiError> {
-    if SERIES_SELECTOR.is_none() {
-        return Ok(Vec::new());
-    }
-
-    Ok(document
-        .select(&safe_static_selector(
-            SERIES_SELECTOR.clone(),
-            SERIES_SELECTOR_TEXT,
-        )?)
-        .map(|elem| {
-            // Extract the entire text content of the li element
-            // This will give us something like "Part 10 of Series Name"
-            let text = elem.text().collect::<String>();
-            // Clean up extra whitespace
-            text.split_whitespace().collect::<Vec<_>>().join(" ")
+/// Helper function to pair each `dt` with the `dd` right after it
+///
+/// AO3 leaves most `dt`/`dd` pairs on series and profile pages without
+/// classes, so the pairs are keyed by the lowercased `dt` text without its colon.
+fn definition_pairs<'a>(
+    labels: impl Iterator<Item = ElementRef<'a>>,
+) -> Vec<(String, ElementRef<'a>)> {
+    labels
+        .filter_map(|dt| {
+            let dd = dt
+                .next_siblings()
+                .find_map(ElementRef::wrap)
+                .filter(|elem| elem.value().name() == "dd")?;
+            let label = dt.text().collect::<String>();
+            Some((label.trim().trim_end_matches(':').to_lowercase(), dd))
         })
-        .filter(|s: &String| !s.is_empty())
-        .collect::<Vec<String>>())
+        .collect()
 }
 
 /// Extract fic metadata from HTML
+#[inline(always)]
 pub fn extract_fic_metadata(item: &str) -> Result<FicMetadata, Ao3ApiError> {
+    extract_fic_metadata_with_base(item, DEFAULT_BASE_URL)
+}
+
+/// Extract fic metadata from HTML served by a mirror or proxy
+///
+/// # Arguments
+/// * `item` - the html of the fic's blurb
+/// * `base_url` - host the page came from, relative links are resolved against it
+pub fn extract_fic_metadata_with_base(
+    item: &str,
+    base_url: &str,
+) -> Result<FicMetadata, Ao3ApiError> {
     let document = Html::parse_document(item);
+    if let Some(gate) = detect_gate(&document) {
+        return Err(Ao3ApiError::Gated(gate));
+    }
 
     let desc: String = document
         .select(&safe_static_selector(
@@ -161,10 +194,12 @@ pub fn extract_fic_metadata(item: &str) -> Result<FicMetadata, Ao3ApiError> {
         .ok_or(Ao3ApiError::SelectorError(
             "Next failed to run when getting link".to_string(),
         ))?;
-    let url = "https://archiveofourown.org".to_owned()
-        + link.attr("href").ok_or(Ao3ApiError::SelectorError(
+    let url = join_url(
+        base_url,
+        link.attr("href").ok_or(Ao3ApiError::SelectorError(
             "Failed to get href attribute from link".to_string(),
-        ))?;
+        ))?,
+    );
     let name = link.text().collect::<String>().trim().to_string();
 
     // Extract ID from URL using compiled regex
@@ -181,7 +216,7 @@ pub fn extract_fic_metadata(item: &str) -> Result<FicMetadata, Ao3ApiError> {
         .to_string();
 
     // Get tags
-    let tags = gettags(item.to_string()).unwrap_or_default();
+    let tags = extract_tags(&document).unwrap_or_default();
 
     // Get last updated date
     let last_updated = document
@@ -238,17 +273,13 @@ pub fn extract_fic_metadata(item: &str) -> Result<FicMetadata, Ao3ApiError> {
     );
 
     // Extract kudos from dd.kudos
-    // The kudos might be in a link or directly in the dd
-    let kudos = parse_number_with_commas(
-        select_raw_text_next!(
-            document,
-            &safe_static_selector(KUDOS_SELECTOR.clone(), KUDOS_SELECTOR_TEXT)?
-        )
-        .ok_or(Ao3ApiError::SelectorError(
-            "Failed to select kudos from dd.kudos".to_string(),
-        ))?
-        .trim(),
-    );
+    // The kudos might be in a link or directly in the dd, works without kudos have no dd.kudos
+    let kudos = select_raw_text_next!(
+        document,
+        &safe_static_selector(KUDOS_SELECTOR.clone(), KUDOS_SELECTOR_TEXT)?
+    )
+    .and_then(|text| parse_number_with_commas(&text).ok())
+    .unwrap_or(0);
     // Extract words from dd.words
     let words = parse_number_with_commas(
         select_raw_text_next!(
@@ -263,7 +294,11 @@ pub fn extract_fic_metadata(item: &str) -> Result<FicMetadata, Ao3ApiError> {
 
     // Extract series from ul.series
     // Format: "Part <strong>10</strong> of <a href="/series/1301696">Series Name</a>"
-    let series = extract_series_list(&document).unwrap_or_default();
+    let series: Vec<String> = extract_series_memberships_with_base(&document, base_url)
+        .unwrap_or_default()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
 
     // Extract hits from dd.hits
     let hits = parse_number_with_commas(
@@ -285,7 +320,7 @@ pub fn extract_fic_metadata(item: &str) -> Result<FicMetadata, Ao3ApiError> {
         .with_ship_type(ship_type)
         .with_language(language)
         .with_chapters(chapters)
-        .with_kudos(Some(kudos.unwrap_or(0)))
+        .with_kudos(Some(kudos))
         .with_words(words.ok())
         .with_series(series)
         .with_hits(hits.ok()))
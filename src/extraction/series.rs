@@ -0,0 +1,316 @@
+//! Extraction for series (`/series/{id}`) and the series a work belongs to
+//!
+//! Blurbs list memberships as `Part <strong>10</strong> of <a>Series</a>`,
+//! work pages as `Part 2 of the <a>Series</a> series`. Both are parsed into
+//! [`SeriesMembership`]s, whose `Display` gives the "Part 10 of Series" form
+//! stored in `FicMetadata::series`.
+use super::{definition_pairs, detect_gate, extract_listing_with_base, parse_number_with_commas};
+use crate::errors::Ao3ApiError;
+use crate::networking::config::DEFAULT_BASE_URL;
+use crate::utils::{
+    html_to_text, join_url, make_selector, safe_static_regex, safe_static_selector,
+};
+use crate::{define_regex, define_selector, make_static};
+use ficdata::FicMetadata;
+use regex::Regex;
+use scraper::{ElementRef, Html, selector::Selector};
+use std::fmt;
+use std::sync::LazyLock;
+
+define_regex!(SERIES_ID_REGEX, SERIES_ID_REGEX_TEXT, r#"/series/(\d+)"#);
+define_regex!(
+    SERIES_PART_REGEX,
+    SERIES_PART_REGEX_TEXT,
+    r#"Part\s+([\d,]+)"#
+);
+define_selector!(
+    MEMBERSHIP_SELECTOR,
+    MEMBERSHIP_SELECTOR_TEXT,
+    r#"ul.series:not(.work) > li, dl.work.meta dd.series span.position"#
+);
+define_selector!(
+    SERIES_LINK_SELECTOR,
+    SERIES_LINK_SELECTOR_TEXT,
+    r#"a[href*="/series/"]"#
+);
+define_selector!(
+    SERIES_TITLE_SELECTOR,
+    SERIES_TITLE_SELECTOR_TEXT,
+    r#"#main h2.heading"#
+);
+define_selector!(
+    SERIES_META_LABEL_SELECTOR,
+    SERIES_META_LABEL_SELECTOR_TEXT,
+    r#"dl.series.meta dt"#
+);
+define_selector!(
+    SERIES_NAV_LINK_SELECTOR,
+    SERIES_NAV_LINK_SELECTOR_TEXT,
+    r#"dl.series.meta a[href*="/series/"], #main > ul.navigation a[href*="/series/"]"#
+);
+define_selector!(
+    SERIES_AUTHOR_SELECTOR,
+    SERIES_AUTHOR_SELECTOR_TEXT,
+    r#"a[rel="author"]"#
+);
+define_selector!(
+    SERIES_USERSTUFF_SELECTOR,
+    SERIES_USERSTUFF_SELECTOR_TEXT,
+    r#"blockquote.userstuff"#
+);
+
+/// A work's place in a series
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeriesMembership {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    /// 1-based position of the work in the series
+    pub part: Option<u32>,
+}
+
+impl fmt::Display for SeriesMembership {
+    /// "Part 10 of Series Name", the form blurbs show
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.part {
+            Some(part) => write!(f, "Part {} of {}", part, self.title),
+            None => f.write_str(&self.title),
+        }
+    }
+}
+
+/// Metadata extracted from a series page
+#[derive(Debug)]
+pub struct SeriesPage {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub creators: Vec<String>,
+    /// Date the first work was posted (`YYYY-MM-DD`)
+    pub begun: Option<String>,
+    /// Date of the last update (`YYYY-MM-DD`)
+    pub updated: Option<String>,
+    pub description: Option<String>,
+    pub notes: Option<String>,
+    pub words: Option<u32>,
+    /// Number of works in the series, including ones not shown on this page
+    pub works_count: Option<u32>,
+    pub complete: Option<bool>,
+    pub bookmarks: Option<u32>,
+    /// Work blurbs on this page in series order, one result per blurb so a
+    /// blurb that fails to parse doesn't fail the page
+    pub works: Vec<Result<FicMetadata, Ao3ApiError>>,
+}
+
+/// Helper function to parse one membership from its `li` or `span.position`
+fn parse_membership(
+    element: ElementRef<'_>,
+    link_selector: &Selector,
+    id_regex: &Regex,
+    part_regex: &Regex,
+    base_url: &str,
+) -> Option<SeriesMembership> {
+    let link = element.select(link_selector).next()?;
+    let href = link.attr("href")?;
+    let id = id_regex.captures(href)?.get(1)?.as_str().to_string();
+    let title = link.text().collect::<String>().trim().to_string();
+    let text = element.text().collect::<String>();
+    let part = part_regex
+        .captures(&text)
+        .and_then(|cap| cap.get(1))
+        .and_then(|part| parse_number_with_commas(part.as_str()).ok());
+    Some(SeriesMembership {
+        id,
+        title,
+        url: join_url(base_url, href),
+        part,
+    })
+}
+
+/// Extract the series a work belongs to
+///
+/// # Arguments
+/// * `document` - a parsed work blurb or work page, a listing page would give the series of every blurb on it
+///
+/// # Returns
+/// * returns the memberships in the order AO3 lists them
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::extraction::extract_series_memberships;
+/// use scraper::Html;
+/// let document = Html::parse_fragment("<ul class=\"series\">...</ul>");
+/// for series in extract_series_memberships(&document).unwrap_or_default() {
+///     println!("{} is part {:?} of series {}", series.title, series.part, series.id);
+/// }
+/// ```
+#[inline(always)]
+pub fn extract_series_memberships(document: &Html) -> Result<Vec<SeriesMembership>, Ao3ApiError> {
+    extract_series_memberships_with_base(document, DEFAULT_BASE_URL)
+}
+
+/// Extract the series a work belongs to from a page served by a mirror or proxy
+///
+/// # Arguments
+/// * `document` - a parsed work blurb or work page
+/// * `base_url` - host the page came from, series links are resolved against it
+pub fn extract_series_memberships_with_base(
+    document: &Html,
+    base_url: &str,
+) -> Result<Vec<SeriesMembership>, Ao3ApiError> {
+    let link_selector =
+        safe_static_selector(SERIES_LINK_SELECTOR.clone(), SERIES_LINK_SELECTOR_TEXT)?;
+    let id_regex = safe_static_regex(SERIES_ID_REGEX.clone(), SERIES_ID_REGEX_TEXT)?;
+    let part_regex = safe_static_regex(SERIES_PART_REGEX.clone(), SERIES_PART_REGEX_TEXT)?;
+    Ok(document
+        .select(&safe_static_selector(
+            MEMBERSHIP_SELECTOR.clone(),
+            MEMBERSHIP_SELECTOR_TEXT,
+        )?)
+        .filter_map(|element| {
+            parse_membership(element, &link_selector, &id_regex, &part_regex, base_url)
+        })
+        .collect())
+}
+
+/// Helper function to find the series id on a series page
+///
+/// The stats and navigation link back to the series. Without bookmarks or a
+/// logged in user neither may be there, but every work blurb still lists its
+/// membership in the series by name.
+fn extract_series_id(document: &Html, title: &str, base_url: &str) -> Result<String, Ao3ApiError> {
+    let regex = safe_static_regex(SERIES_ID_REGEX.clone(), SERIES_ID_REGEX_TEXT)?;
+    if let Some(id) = document
+        .select(&safe_static_selector(
+            SERIES_NAV_LINK_SELECTOR.clone(),
+            SERIES_NAV_LINK_SELECTOR_TEXT,
+        )?)
+        .filter_map(|elem| elem.attr("href"))
+        .find_map(|href| regex.captures(href))
+        .and_then(|cap| cap.get(1))
+    {
+        return Ok(id.as_str().to_string());
+    }
+
+    extract_series_memberships_with_base(document, base_url)?
+        .into_iter()
+        .find(|series| series.title == title)
+        .map(|series| series.id)
+        .ok_or(Ao3ApiError::SelectorError(
+            "Failed to find series id on series page".to_string(),
+        ))
+}
+
+/// Extract a series page
+///
+/// Series with more than 20 works are paginated, the remaining blurbs can be
+/// fetched with [`WorkListing`](crate::networking::WorkListing) on the series URL.
+///
+/// # Arguments
+/// * `document` - the parsed series page, e.g. `/series/123`
+///
+/// # Returns
+/// * returns a [`SeriesPage`] with the series metadata and the work blurbs on the page
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::extraction::extract_series_page;
+/// use ao3_api_rs::networking::{create_client, get_page};
+/// use scraper::Html;
+/// let client = create_client("test").unwrap();
+/// let text = get_page("/series/123", &client).unwrap().text().unwrap();
+/// let series = extract_series_page(&Html::parse_document(&text)).unwrap();
+/// println!("{} has {:?} works", series.title, series.works_count);
+/// ```
+#[inline(always)]
+pub fn extract_series_page(document: &Html) -> Result<SeriesPage, Ao3ApiError> {
+    extract_series_page_with_base(document, DEFAULT_BASE_URL)
+}
+
+/// Extract a series page served by a mirror or proxy
+///
+/// # Arguments
+/// * `document` - the parsed series page
+/// * `base_url` - host the page came from, series and work links are resolved against it
+pub fn extract_series_page_with_base(
+    document: &Html,
+    base_url: &str,
+) -> Result<SeriesPage, Ao3ApiError> {
+    if let Some(gate) = detect_gate(document) {
+        return Err(Ao3ApiError::Gated(gate));
+    }
+    let title = document
+        .select(&safe_static_selector(
+            SERIES_TITLE_SELECTOR.clone(),
+            SERIES_TITLE_SELECTOR_TEXT,
+        )?)
+        .next()
+        .map(|elem| elem.text().collect::<String>().trim().to_string())
+        .ok_or(Ao3ApiError::SelectorError(
+            "Failed to select title from series page".to_string(),
+        ))?;
+    let id = extract_series_id(document, &title, base_url)?;
+
+    let mut series = SeriesPage {
+        url: join_url(base_url, &format!("/series/{}", id)),
+        id,
+        title,
+        creators: Vec::new(),
+        begun: None,
+        updated: None,
+        description: None,
+        notes: None,
+        words: None,
+        works_count: None,
+        complete: None,
+        bookmarks: None,
+        works: extract_listing_with_base(document, base_url),
+    };
+
+    // dl.series.meta is a list of unclassed dt/dd pairs, the dt text says what the dd holds
+    let author_selector =
+        safe_static_selector(SERIES_AUTHOR_SELECTOR.clone(), SERIES_AUTHOR_SELECTOR_TEXT)?;
+    let userstuff_selector = safe_static_selector(
+        SERIES_USERSTUFF_SELECTOR.clone(),
+        SERIES_USERSTUFF_SELECTOR_TEXT,
+    )?;
+    for (label, dd) in definition_pairs(document.select(&safe_static_selector(
+        SERIES_META_LABEL_SELECTOR.clone(),
+        SERIES_META_LABEL_SELECTOR_TEXT,
+    )?)) {
+        let text = dd.text().collect::<String>().trim().to_string();
+        match label.as_str() {
+            "creator" | "creators" => {
+                series.creators = dd
+                    .select(&author_selector)
+                    .map(|elem| elem.text().collect::<String>().trim().to_string())
+                    .collect();
+                // anonymous and orphaned series have no author links
+                if series.creators.is_empty() && !text.is_empty() {
+                    series.creators = text.split(", ").map(str::to_string).collect();
+                }
+            }
+            "series begun" => series.begun = Some(text),
+            "series updated" => series.updated = Some(text),
+            label @ ("description" | "notes") => {
+                let value = dd
+                    .select(&userstuff_selector)
+                    .next()
+                    .map(html_to_text)
+                    .unwrap_or(text);
+                if label == "description" {
+                    series.description = Some(value);
+                } else {
+                    series.notes = Some(value);
+                }
+            }
+            "words" => series.words = parse_number_with_commas(&text).ok(),
+            "works" => series.works_count = parse_number_with_commas(&text).ok(),
+            "complete" => series.complete = Some(text.eq_ignore_ascii_case("yes")),
+            "bookmarks" => series.bookmarks = parse_number_with_commas(&text).ok(),
+            _ => {}
+        }
+    }
+
+    Ok(series)
+}
@@ -0,0 +1,539 @@
+//! Extraction for tag pages (`/tags/{name}`)
+//!
+//! Tag wranglers link every tag a creator types to a canonical one, either by
+//! making it a synonym or by leaving it unfilterable. A tag page shows where a
+//! tag sits in that graph, which is what's needed to map the free-text values
+//! in a `TagMap` onto the names AO3 filters by.
+//!
+//! [`Tag`] and [`TagCategory`] are a typed view of a `TagMap`, see
+//! [`tags_from_map`] and [`tags_to_map`].
+use super::detect_gate;
+use crate::errors::Ao3ApiError;
+use crate::networking::config::DEFAULT_BASE_URL;
+use crate::utils::{
+    join_url, make_selector, percent_encode, safe_static_regex, safe_static_selector,
+};
+use crate::{define_regex, define_selector, make_static};
+use ficdata::TagMap;
+use regex::Regex;
+use scraper::{ElementRef, Html, selector::Selector};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::LazyLock;
+
+define_regex!(
+    TAG_TYPE_REGEX,
+    TAG_TYPE_REGEX_TEXT,
+    r#"belongs to the (.+?) Category"#
+);
+define_regex!(
+    CANONICAL_REGEX,
+    CANONICAL_REGEX_TEXT,
+    r#"It.s a (?:canonical|common) tag"#
+);
+define_selector!(
+    TAG_NAME_SELECTOR,
+    TAG_NAME_SELECTOR_TEXT,
+    r#"#main div.primary.header h2.heading, #main > h2.heading"#
+);
+define_selector!(
+    TAG_PARAGRAPH_SELECTOR,
+    TAG_PARAGRAPH_SELECTOR_TEXT,
+    r#"#main p"#
+);
+define_selector!(TAG_LINK_SELECTOR, TAG_LINK_SELECTOR_TEXT, r#"a.tag"#);
+define_selector!(
+    TAG_ITEM_SELECTOR,
+    TAG_ITEM_SELECTOR_TEXT,
+    r#"ul.tags li[class]"#
+);
+define_selector!(
+    PARENT_TAGS_SELECTOR,
+    PARENT_TAGS_SELECTOR_TEXT,
+    r#"div.parent.listbox a.tag"#
+);
+define_selector!(
+    SYNONYM_TAGS_SELECTOR,
+    SYNONYM_TAGS_SELECTOR_TEXT,
+    r#"div.synonym.listbox a.tag"#
+);
+define_selector!(
+    METATAGS_SELECTOR,
+    METATAGS_SELECTOR_TEXT,
+    r#"div.meta.listbox a.tag"#
+);
+define_selector!(
+    SUBTAGS_SELECTOR,
+    SUBTAGS_SELECTOR_TEXT,
+    r#"div.sub.listbox a.tag"#
+);
+
+/// Kind of tag, as named by "This tag belongs to the ... Category"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagType {
+    /// A tag that can be put on works, listed under its category
+    Work(TagCategory),
+    /// Fandom groupings such as "Anime & Manga"
+    Media,
+    /// Tags wranglers haven't sorted yet
+    Unsorted,
+}
+
+impl TagType {
+    /// Helper function to parse the category name shown on a tag page
+    fn from_label(label: &str) -> Option<Self> {
+        match label.trim().to_lowercase().as_str() {
+            "additional tags" => Some(TagType::Work(TagCategory::Freeform)),
+            "media" => Some(TagType::Media),
+            "unsorted tag" | "unsorted tags" | "unsorted" => Some(TagType::Unsorted),
+            // "Archive Warning" and the singular names of the other categories
+            label => TagCategory::from_key(label).map(TagType::Work),
+        }
+    }
+
+    /// Category works list tags of this type under, `None` for media and unsorted tags
+    pub fn category(self) -> Option<TagCategory> {
+        match self {
+            TagType::Work(category) => Some(category),
+            TagType::Media | TagType::Unsorted => None,
+        }
+    }
+}
+
+impl From<TagCategory> for TagType {
+    fn from(category: TagCategory) -> Self {
+        TagType::Work(category)
+    }
+}
+
+/// Metadata extracted from a tag page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagPage {
+    pub name: String,
+    pub url: String,
+    /// Listing of the works with this tag, see [`WorkListing::for_tag`](crate::networking::WorkListing::for_tag)
+    pub works_url: String,
+    pub tag_type: Option<TagType>,
+    /// Canonical tags can be filtered on
+    pub canonical: bool,
+    /// Canonical tag this one is a synonym of
+    pub merger: Option<String>,
+    /// More general tags, for characters and relationships this includes their fandoms
+    pub parents: Vec<String>,
+    /// Tags that have been made synonyms of this one
+    pub synonyms: Vec<String>,
+    pub metatags: Vec<String>,
+    /// More specific tags, subtags of subtags included
+    pub subtags: Vec<String>,
+}
+
+impl TagPage {
+    /// Name AO3 files works with this tag under, the merger for synonyms
+    pub fn canonical_name(&self) -> &str {
+        self.merger.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Category a work's tag is listed under, the keys of a `TagMap`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TagCategory {
+    Rating,
+    Warning,
+    Category,
+    Fandom,
+    Relationship,
+    Character,
+    /// "Additional Tags"
+    Freeform,
+}
+
+impl TagCategory {
+    /// Every category, in the order AO3 shows them
+    pub const ALL: [TagCategory; 7] = [
+        TagCategory::Rating,
+        TagCategory::Warning,
+        TagCategory::Category,
+        TagCategory::Fandom,
+        TagCategory::Relationship,
+        TagCategory::Character,
+        TagCategory::Freeform,
+    ];
+
+    /// Key used for the category in a `TagMap`, e.g. `relationships`
+    pub fn key(self) -> &'static str {
+        match self {
+            TagCategory::Rating => "ratings",
+            TagCategory::Warning => "warnings",
+            TagCategory::Category => "categories",
+            TagCategory::Fandom => "fandoms",
+            TagCategory::Relationship => "relationships",
+            TagCategory::Character => "characters",
+            TagCategory::Freeform => "freeforms",
+        }
+    }
+
+    /// Parse a `TagMap` key or the class of the element a tag was found in
+    ///
+    /// Singular, plural and compound classes are accepted, so `warning`,
+    /// `warnings` and `warnings last` all give [`TagCategory::Warning`].
+    ///
+    /// # Example
+    /// ```
+    /// use ao3_api_rs::extraction::TagCategory;
+    /// assert_eq!(TagCategory::from_key("freeform tags"), Some(TagCategory::Freeform));
+    /// assert_eq!(TagCategory::from_key("series"), None);
+    /// ```
+    pub fn from_key(key: &str) -> Option<Self> {
+        key.split_whitespace()
+            .find_map(|class| match class.to_lowercase().as_str() {
+                "rating" | "ratings" => Some(TagCategory::Rating),
+                "warning" | "warnings" => Some(TagCategory::Warning),
+                "category" | "categories" => Some(TagCategory::Category),
+                "fandom" | "fandoms" => Some(TagCategory::Fandom),
+                "relationship" | "relationships" => Some(TagCategory::Relationship),
+                "character" | "characters" => Some(TagCategory::Character),
+                "freeform" | "freeforms" => Some(TagCategory::Freeform),
+                _ => None,
+            })
+    }
+}
+
+impl fmt::Display for TagCategory {
+    /// The `TagMap` key
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
+/// A tag on a work
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tag {
+    pub category: TagCategory,
+    /// Name as AO3 displays it
+    pub name: String,
+    /// Path segment of the tag's URL, e.g. `Hurt*s*Comfort`
+    pub slug: String,
+}
+
+impl Tag {
+    /// Tag with a slug derived from its name, see [`tag_slug`]
+    ///
+    /// The extractors read the slug from the tag's link instead, use this when
+    /// only the name is known, e.g. for tags from a `TagMap`.
+    pub fn new(category: TagCategory, name: impl Into<String>) -> Self {
+        let name = name.into();
+        Self {
+            category,
+            slug: tag_slug(&name),
+            name,
+        }
+    }
+
+    /// Path of the tag's page, e.g. `/tags/Hurt*s*Comfort`
+    pub fn path(&self) -> String {
+        format!("/tags/{}", self.slug)
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// Turn a `TagMap` into typed tags
+///
+/// # Arguments
+/// * `tags` - a `TagMap` such as `FicMetadata::tags`
+///
+/// # Returns
+/// * returns the tags ordered by category, in AO3's order, then as listed.
+///   Keys that aren't a known category are skipped
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::extraction::{TagCategory, extract_fic_metadata, tags_from_map};
+/// let metadata = extract_fic_metadata("<li role=\"article\">...</li>").unwrap();
+/// for tag in tags_from_map(&metadata.tags) {
+///     if tag.category == TagCategory::Relationship {
+///         println!("{} ({})", tag.name, tag.path());
+///     }
+/// }
+/// ```
+pub fn tags_from_map(tags: &TagMap) -> Vec<Tag> {
+    let mut typed: Vec<(TagCategory, &Vec<String>)> = tags
+        .iter()
+        .filter_map(|(key, values)| Some((TagCategory::from_key(key)?, values)))
+        .collect();
+    // HashMap order is random, and two odd keys can share a category
+    typed.sort_by(|(a, a_values), (b, b_values)| a.cmp(b).then_with(|| a_values.cmp(b_values)));
+    typed
+        .into_iter()
+        .flat_map(|(category, values)| values.iter().map(move |name| Tag::new(category, name)))
+        .collect()
+}
+
+/// Turn typed tags back into a `TagMap`, keyed by [`TagCategory::key`]
+pub fn tags_to_map(tags: &[Tag]) -> TagMap {
+    let mut map: HashMap<String, Vec<String>> = HashMap::new();
+    for tag in tags {
+        map.entry(tag.category.key().to_string())
+            .or_default()
+            .push(tag.name.clone());
+    }
+    map
+}
+
+/// Turn a tag name into the path segment AO3 uses for it
+///
+/// AO3 replaces characters that would break its routes with `*x*` escapes
+/// before percent-encoding, so "Hurt/Comfort" lives at `/tags/Hurt*s*Comfort`.
+///
+/// # Example
+/// ```
+/// use ao3_api_rs::extraction::tag_slug;
+/// assert_eq!(tag_slug("Hurt/Comfort"), "Hurt*s*Comfort");
+/// assert_eq!(tag_slug("Dr. Who & Friends"), "Dr*d*%20Who%20*a*%20Friends");
+/// ```
+pub fn tag_slug(name: &str) -> String {
+    let escaped = name
+        .replace('/', "*s*")
+        .replace('&', "*a*")
+        .replace('.', "*d*")
+        .replace('?', "*q*")
+        .replace('#', "*h*");
+    percent_encode(&escaped)
+}
+
+/// Gets all tags from the `ul.tags` lists of a blurb
+///
+/// Each `li` is keyed by its class, normalised with [`TagCategory::from_key`]
+/// when it names a known category and kept as is otherwise.
+///
+/// # Arguments
+/// * `document` - a parsed work, series or external work blurb
+///
+/// # Returns
+/// * returns a HashMap mapping tag categories to vectors of tag values
+///
+/// # Example
+/// ```
+/// use ao3_api_rs::extraction::extract_tags;
+/// use scraper::Html;
+/// let document = Html::parse_fragment(
+///     r#"<ul class="tags commas"><li class="freeforms"><a class="tag" href="/tags/Tea%20*a*%20Biscuits/works">Tea &amp; Biscuits</a></li></ul>"#,
+/// );
+/// let tags = extract_tags(&document).unwrap();
+/// assert_eq!(tags["freeforms"], vec!["Tea & Biscuits"]);
+/// ```
+pub fn extract_tags(document: &Html) -> Result<TagMap, Ao3ApiError> {
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    let link_selector = safe_static_selector(TAG_LINK_SELECTOR.clone(), TAG_LINK_SELECTOR_TEXT)?;
+
+    for item in document.select(&safe_static_selector(
+        TAG_ITEM_SELECTOR.clone(),
+        TAG_ITEM_SELECTOR_TEXT,
+    )?) {
+        let class = item.attr("class").unwrap_or_default().trim();
+        let Some(link) = item.select(&link_selector).next() else {
+            continue;
+        };
+        let name = link
+            .text()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if class.is_empty() || name.is_empty() {
+            continue;
+        }
+        let key = TagCategory::from_key(class)
+            .map(|category| category.key().to_string())
+            .unwrap_or_else(|| class.to_string());
+        tags.entry(key).or_default().push(name);
+    }
+
+    Ok(tags)
+}
+
+/// Gets the tags from the `ul.tags` lists of a blurb, with the slugs they link to
+///
+/// Unlike [`extract_tags`], items whose class isn't a known category are skipped.
+///
+/// # Arguments
+/// * `document` - a parsed work, series or external work blurb
+///
+/// # Returns
+/// * returns the tags in the order the blurb lists them
+///
+/// # Example
+/// ```
+/// use ao3_api_rs::extraction::{TagCategory, extract_typed_tags};
+/// use scraper::Html;
+/// let document = Html::parse_fragment(
+///     r#"<ul class="tags commas"><li class="freeforms"><a class="tag" href="/tags/Tea%20*a*%20Biscuits/works">Tea &amp; Biscuits</a></li></ul>"#,
+/// );
+/// let tags = extract_typed_tags(&document).unwrap();
+/// assert_eq!(tags[0].category, TagCategory::Freeform);
+/// assert_eq!(tags[0].slug, "Tea%20*a*%20Biscuits");
+/// ```
+pub fn extract_typed_tags(document: &Html) -> Result<Vec<Tag>, Ao3ApiError> {
+    let link_selector = safe_static_selector(TAG_LINK_SELECTOR.clone(), TAG_LINK_SELECTOR_TEXT)?;
+    let mut tags = Vec::new();
+
+    for item in document.select(&safe_static_selector(
+        TAG_ITEM_SELECTOR.clone(),
+        TAG_ITEM_SELECTOR_TEXT,
+    )?) {
+        let Some(category) = item.attr("class").and_then(TagCategory::from_key) else {
+            continue;
+        };
+        if let Some(link) = item.select(&link_selector).next() {
+            tags.extend(tag_from_link(category, link));
+        }
+    }
+
+    Ok(tags)
+}
+
+/// Helper function to build a tag from its `a.tag` link
+///
+/// The slug is read from the href, names are only slugified when it has none.
+pub(crate) fn tag_from_link(category: TagCategory, link: ElementRef) -> Option<Tag> {
+    let name = link
+        .text()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if name.is_empty() {
+        return None;
+    }
+    let slug = link
+        .attr("href")
+        .and_then(slug_from_href)
+        .unwrap_or_else(|| tag_slug(&name));
+    Some(Tag {
+        category,
+        name,
+        slug,
+    })
+}
+
+/// Helper function to get the path segment after `/tags/` in a tag link
+fn slug_from_href(href: &str) -> Option<String> {
+    let (_, rest) = href.split_once("/tags/")?;
+    let slug = rest.split(['/', '?', '#']).next()?;
+    (!slug.is_empty()).then(|| slug.to_string())
+}
+
+/// Helper function to collect the names of the tags a selector matches
+fn tag_names(document: &Html, selector: &Selector) -> Vec<String> {
+    document
+        .select(selector)
+        .map(|elem| elem.text().collect::<String>().trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Extract a tag page
+///
+/// # Arguments
+/// * `document` - the parsed tag page, e.g. `/tags/Hurt*s*Comfort`
+///
+/// # Returns
+/// * returns a [`TagPage`] with the tag's type, canonical status and related tags
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::extraction::{extract_tag_page, tag_slug};
+/// use ao3_api_rs::networking::{create_client, get_page};
+/// use scraper::Html;
+/// let client = create_client("test").unwrap();
+/// let path = format!("/tags/{}", tag_slug("h/c"));
+/// let text = get_page(&path, &client).unwrap().text().unwrap();
+/// let tag = extract_tag_page(&Html::parse_document(&text)).unwrap();
+/// println!("{} is filed under {}", tag.name, tag.canonical_name());
+/// ```
+#[inline(always)]
+pub fn extract_tag_page(document: &Html) -> Result<TagPage, Ao3ApiError> {
+    extract_tag_page_with_base(document, DEFAULT_BASE_URL)
+}
+
+/// Extract a tag page served by a mirror or proxy
+///
+/// # Arguments
+/// * `document` - the parsed tag page
+/// * `base_url` - host the page came from, the tag URLs are resolved against it
+pub fn extract_tag_page_with_base(document: &Html, base_url: &str) -> Result<TagPage, Ao3ApiError> {
+    if let Some(gate) = detect_gate(document) {
+        return Err(Ao3ApiError::Gated(gate));
+    }
+    let name = document
+        .select(&safe_static_selector(
+            TAG_NAME_SELECTOR.clone(),
+            TAG_NAME_SELECTOR_TEXT,
+        )?)
+        .next()
+        .map(|elem| elem.text().collect::<String>().trim().to_string())
+        .ok_or(Ao3ApiError::SelectorError(
+            "Failed to select name from tag page".to_string(),
+        ))?;
+    let path = format!("/tags/{}", tag_slug(&name));
+
+    let type_regex = safe_static_regex(TAG_TYPE_REGEX.clone(), TAG_TYPE_REGEX_TEXT)?;
+    let canonical_regex = safe_static_regex(CANONICAL_REGEX.clone(), CANONICAL_REGEX_TEXT)?;
+    let link_selector = safe_static_selector(TAG_LINK_SELECTOR.clone(), TAG_LINK_SELECTOR_TEXT)?;
+    let mut tag_type = None;
+    let mut canonical = false;
+    let mut merger = None;
+    for paragraph in document.select(&safe_static_selector(
+        TAG_PARAGRAPH_SELECTOR.clone(),
+        TAG_PARAGRAPH_SELECTOR_TEXT,
+    )?) {
+        let text = paragraph
+            .text()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if let Some(cap) = type_regex.captures(&text) {
+            tag_type = cap
+                .get(1)
+                .and_then(|label| TagType::from_label(label.as_str()));
+            canonical |= canonical_regex.is_match(&text);
+        }
+        // "h/c has been made a synonym of Hurt/Comfort"
+        if merger.is_none() && text.contains("made a synonym of") {
+            merger = paragraph
+                .select(&link_selector)
+                .next()
+                .map(|elem| elem.text().collect::<String>().trim().to_string());
+        }
+    }
+
+    Ok(TagPage {
+        url: join_url(base_url, &path),
+        works_url: join_url(base_url, &format!("{}/works", path)),
+        name,
+        tag_type,
+        canonical,
+        merger,
+        parents: tag_names(
+            document,
+            &safe_static_selector(PARENT_TAGS_SELECTOR.clone(), PARENT_TAGS_SELECTOR_TEXT)?,
+        ),
+        synonyms: tag_names(
+            document,
+            &safe_static_selector(SYNONYM_TAGS_SELECTOR.clone(), SYNONYM_TAGS_SELECTOR_TEXT)?,
+        ),
+        metatags: tag_names(
+            document,
+            &safe_static_selector(METATAGS_SELECTOR.clone(), METATAGS_SELECTOR_TEXT)?,
+        ),
+        subtags: tag_names(
+            document,
+            &safe_static_selector(SUBTAGS_SELECTOR.clone(), SUBTAGS_SELECTOR_TEXT)?,
+        ),
+    })
+}
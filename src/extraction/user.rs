@@ -0,0 +1,563 @@
+//! Extraction for user pages (`/users/{name}/...`)
+//!
+//! Works belong to pseuds rather than accounts: a byline links to
+//! `/users/{name}/pseuds/{pseud}` and only shows the account name when the
+//! pseud differs from it, e.g. "Robin (birdwatcher)". [`Pseud`] keeps both so
+//! co-authored works can be matched to the right account.
+use super::{
+    Bookmark, ListingInfo, definition_pairs, detect_gate, extract_bookmarks_with_base,
+    extract_fic_metadata_with_base, extract_listing_info, parse_number_with_commas,
+};
+use crate::errors::Ao3ApiError;
+use crate::networking::config::DEFAULT_BASE_URL;
+use crate::utils::{
+    html_to_text, join_url, make_selector, percent_decode, safe_static_regex, safe_static_selector,
+};
+use crate::{define_regex, define_selector, make_static, select_raw_text, select_text};
+use ficdata::FicMetadata;
+use regex::Regex;
+use scraper::{ElementRef, Html, selector::Selector};
+use std::sync::LazyLock;
+
+define_regex!(
+    USER_PATH_REGEX,
+    USER_PATH_REGEX_TEXT,
+    r#"/users/([^/?#]+)(?:/pseuds/([^/?#]+))?"#
+);
+define_regex!(SERIES_ID_REGEX, SERIES_ID_REGEX_TEXT, r#"/series/(\d+)"#);
+define_selector!(
+    OWNER_SELECTOR,
+    OWNER_SELECTOR_TEXT,
+    r#"div.user.home div.primary.header h2.heading a[href*="/users/"], #dashboard a[href*="/users/"]"#
+);
+define_selector!(
+    PROFILE_META_LABEL_SELECTOR,
+    PROFILE_META_LABEL_SELECTOR_TEXT,
+    r#"div.user.home.profile dl.meta dt"#
+);
+define_selector!(
+    PROFILE_BIO_SELECTOR,
+    PROFILE_BIO_SELECTOR_TEXT,
+    r#"div.user.home.profile div.bio blockquote.userstuff"#
+);
+define_selector!(
+    PSEUD_LINK_SELECTOR,
+    PSEUD_LINK_SELECTOR_TEXT,
+    r#"a[href*="/users/"]"#
+);
+define_selector!(
+    CREATOR_SELECTOR,
+    CREATOR_SELECTOR_TEXT,
+    r#"h4.heading a[rel="author"], h3.byline a[rel="author"]"#
+);
+define_selector!(
+    WORK_BLURB_SELECTOR,
+    WORK_BLURB_SELECTOR_TEXT,
+    r#"li.work.blurb"#
+);
+define_selector!(
+    SERIES_BLURB_SELECTOR,
+    SERIES_BLURB_SELECTOR_TEXT,
+    r#"li.series.blurb"#
+);
+define_selector!(
+    SERIES_BLURB_LINK_SELECTOR,
+    SERIES_BLURB_LINK_SELECTOR_TEXT,
+    r#"h4.heading a[href*="/series/"]"#
+);
+define_selector!(
+    SERIES_FANDOM_SELECTOR,
+    SERIES_FANDOM_SELECTOR_TEXT,
+    r#"h5.fandoms a.tag"#
+);
+define_selector!(
+    SERIES_DATETIME_SELECTOR,
+    SERIES_DATETIME_SELECTOR_TEXT,
+    r#"p.datetime"#
+);
+define_selector!(
+    SERIES_SUMMARY_SELECTOR,
+    SERIES_SUMMARY_SELECTOR_TEXT,
+    r#"blockquote.userstuff.summary"#
+);
+define_selector!(
+    SERIES_COMPLETE_SELECTOR,
+    SERIES_COMPLETE_SELECTOR_TEXT,
+    r#"ul.required-tags span.iswip"#
+);
+define_selector!(
+    SERIES_WORDS_SELECTOR,
+    SERIES_WORDS_SELECTOR_TEXT,
+    r#"dl.stats dd.words"#
+);
+define_selector!(
+    SERIES_WORKS_SELECTOR,
+    SERIES_WORKS_SELECTOR_TEXT,
+    r#"dl.stats dd.works"#
+);
+define_selector!(
+    SERIES_BOOKMARKS_SELECTOR,
+    SERIES_BOOKMARKS_SELECTOR_TEXT,
+    r#"dl.stats dd.bookmarks"#
+);
+
+/// A pseud of an account
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pseud {
+    /// Account name, the `{name}` in `/users/{name}`
+    pub user: String,
+    /// Pseud name, the same as `user` for the default pseud
+    pub name: String,
+    pub url: String,
+}
+
+impl Pseud {
+    /// Parse a `/users/{name}` or `/users/{name}/pseuds/{pseud}` link
+    ///
+    /// # Arguments
+    /// * `href` - the link, relative or absolute
+    /// * `base_url` - host the link came from
+    pub fn from_href(href: &str, base_url: &str) -> Option<Pseud> {
+        let regex = safe_static_regex(USER_PATH_REGEX.clone(), USER_PATH_REGEX_TEXT).ok()?;
+        let cap = regex.captures(href)?;
+        let user = percent_decode(cap.get(1)?.as_str());
+        let name = cap
+            .get(2)
+            .map(|pseud| percent_decode(pseud.as_str()))
+            .unwrap_or_else(|| user.clone());
+        let path = format!("/users/{}/pseuds/{}", user, name);
+        Some(Pseud {
+            url: join_url(base_url, &path),
+            user,
+            name,
+        })
+    }
+
+    /// Whether this is the account's default pseud
+    pub fn is_default(&self) -> bool {
+        self.name == self.user
+    }
+}
+
+/// Metadata extracted from a profile page (`/users/{name}/profile`)
+#[derive(Debug, Clone)]
+pub struct UserProfile {
+    pub user: String,
+    pub url: String,
+    /// Numeric account id
+    pub user_id: Option<String>,
+    /// Date the account was created (`YYYY-MM-DD`)
+    pub joined: Option<String>,
+    pub pseuds: Vec<Pseud>,
+    pub bio: Option<String>,
+}
+
+/// A work blurb from a user's works, gifts or bookmarks
+#[derive(Debug, Clone)]
+pub struct UserWork {
+    pub metadata: FicMetadata,
+    /// Pseuds in the byline, `metadata.authors` holds their display names
+    pub creators: Vec<Pseud>,
+}
+
+/// A series blurb from a user's series listing
+#[derive(Debug, Clone)]
+pub struct SeriesBlurb {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub creators: Vec<Pseud>,
+    pub fandoms: Vec<String>,
+    /// Date of the last update as shown on the blurb, e.g. "17 Feb 2024"
+    pub updated: Option<String>,
+    pub description: Option<String>,
+    pub words: Option<u32>,
+    pub works_count: Option<u32>,
+    pub bookmarks: Option<u32>,
+    pub complete: Option<bool>,
+}
+
+/// One page of a user's works, series, bookmarks or gifts
+#[derive(Debug)]
+pub struct UserListing<T> {
+    /// Whose page this is, a pseud when the page is `/users/{name}/pseuds/{pseud}/...`
+    pub owner: Option<Pseud>,
+    pub info: ListingInfo,
+    /// One result per blurb, so a blurb that fails to parse doesn't fail the page
+    pub items: Vec<Result<T, Ao3ApiError>>,
+}
+
+/// Helper function to find whose user page `document` is
+fn extract_owner(document: &Html, base_url: &str) -> Result<Option<Pseud>, Ao3ApiError> {
+    Ok(document
+        .select(&safe_static_selector(
+            OWNER_SELECTOR.clone(),
+            OWNER_SELECTOR_TEXT,
+        )?)
+        .filter_map(|link| link.attr("href"))
+        .find_map(|href| Pseud::from_href(href, base_url)))
+}
+
+/// Helper function to collect the pseuds linked from `element`'s bylines
+fn creators_of(element: ElementRef<'_>, selector: &Selector, base_url: &str) -> Vec<Pseud> {
+    element
+        .select(selector)
+        .filter_map(|link| link.attr("href"))
+        .filter_map(|href| Pseud::from_href(href, base_url))
+        .collect()
+}
+
+/// Extract the pseuds credited on a work blurb or work page
+///
+/// # Arguments
+/// * `document` - a parsed work blurb or work page
+///
+/// # Returns
+/// * returns the pseuds in byline order, anonymous works have none
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::extraction::extract_creators;
+/// use scraper::Html;
+/// let document = Html::parse_fragment("<h4 class=\"heading\">...</h4>");
+/// for pseud in extract_creators(&document).unwrap_or_default() {
+///     println!("{} writing as {}", pseud.user, pseud.name);
+/// }
+/// ```
+#[inline(always)]
+pub fn extract_creators(document: &Html) -> Result<Vec<Pseud>, Ao3ApiError> {
+    extract_creators_with_base(document, DEFAULT_BASE_URL)
+}
+
+/// Extract the pseuds credited on a page served by a mirror or proxy
+///
+/// # Arguments
+/// * `document` - a parsed work blurb or work page
+/// * `base_url` - host the page came from, pseud links are resolved against it
+pub fn extract_creators_with_base(
+    document: &Html,
+    base_url: &str,
+) -> Result<Vec<Pseud>, Ao3ApiError> {
+    let selector = safe_static_selector(CREATOR_SELECTOR.clone(), CREATOR_SELECTOR_TEXT)?;
+    Ok(creators_of(document.root_element(), &selector, base_url))
+}
+
+/// Extract a user's profile page
+///
+/// # Arguments
+/// * `document` - the parsed profile page, e.g. `/users/name/profile`
+///
+/// # Returns
+/// * returns a [`UserProfile`] with the account's pseuds, join date, id and bio
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::extraction::extract_user_profile;
+/// use ao3_api_rs::networking::{create_client, get_page};
+/// use scraper::Html;
+/// let client = create_client("test").unwrap();
+/// let text = get_page("/users/name/profile", &client).unwrap().text().unwrap();
+/// let profile = extract_user_profile(&Html::parse_document(&text)).unwrap();
+/// println!("{} joined on {:?}", profile.user, profile.joined);
+/// ```
+#[inline(always)]
+pub fn extract_user_profile(document: &Html) -> Result<UserProfile, Ao3ApiError> {
+    extract_user_profile_with_base(document, DEFAULT_BASE_URL)
+}
+
+/// Extract a user's profile page served by a mirror or proxy
+///
+/// # Arguments
+/// * `document` - the parsed profile page
+/// * `base_url` - host the page came from, pseud links are resolved against it
+pub fn extract_user_profile_with_base(
+    document: &Html,
+    base_url: &str,
+) -> Result<UserProfile, Ao3ApiError> {
+    if let Some(gate) = detect_gate(document) {
+        return Err(Ao3ApiError::Gated(gate));
+    }
+    let pseud_selector =
+        safe_static_selector(PSEUD_LINK_SELECTOR.clone(), PSEUD_LINK_SELECTOR_TEXT)?;
+    let mut user_id = None;
+    let mut joined = None;
+    let mut pseuds = Vec::new();
+    for (label, dd) in definition_pairs(document.select(&safe_static_selector(
+        PROFILE_META_LABEL_SELECTOR.clone(),
+        PROFILE_META_LABEL_SELECTOR_TEXT,
+    )?)) {
+        let text = dd.text().collect::<String>().trim().to_string();
+        match label.as_str() {
+            "my pseuds" => pseuds = creators_of(dd, &pseud_selector, base_url),
+            "i joined on" => joined = Some(text),
+            "my user id is" => user_id = Some(text),
+            _ => {}
+        }
+    }
+
+    let user = extract_owner(document, base_url)?
+        .or_else(|| pseuds.first().cloned())
+        .map(|pseud| pseud.user)
+        .ok_or(Ao3ApiError::SelectorError(
+            "Failed to find user name on profile page".to_string(),
+        ))?;
+    let bio = document
+        .select(&safe_static_selector(
+            PROFILE_BIO_SELECTOR.clone(),
+            PROFILE_BIO_SELECTOR_TEXT,
+        )?)
+        .next()
+        .map(html_to_text)
+        .filter(|bio| !bio.is_empty());
+
+    Ok(UserProfile {
+        url: join_url(base_url, &format!("/users/{}", user)),
+        user,
+        user_id,
+        joined,
+        pseuds,
+        bio,
+    })
+}
+
+/// Helper function to extract a listing of work blurbs with their creators
+fn extract_user_work_listing(
+    document: &Html,
+    base_url: &str,
+) -> Result<UserListing<UserWork>, Ao3ApiError> {
+    if let Some(gate) = detect_gate(document) {
+        return Err(Ao3ApiError::Gated(gate));
+    }
+    let creator_selector = safe_static_selector(CREATOR_SELECTOR.clone(), CREATOR_SELECTOR_TEXT)?;
+    let items = document
+        .select(&safe_static_selector(
+            WORK_BLURB_SELECTOR.clone(),
+            WORK_BLURB_SELECTOR_TEXT,
+        )?)
+        .map(|blurb| {
+            Ok(UserWork {
+                metadata: extract_fic_metadata_with_base(&blurb.html(), base_url)?,
+                creators: creators_of(blurb, &creator_selector, base_url),
+            })
+        })
+        .collect();
+    Ok(UserListing {
+        owner: extract_owner(document, base_url)?,
+        info: extract_listing_info(document)?,
+        items,
+    })
+}
+
+/// Extract a page of a user's or pseud's works
+///
+/// Further pages can be fetched with [`WorkListing`](crate::networking::WorkListing).
+///
+/// # Arguments
+/// * `document` - the parsed works page, e.g. `/users/name/works` or `/users/name/pseuds/pseud/works`
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::extraction::extract_user_works;
+/// use scraper::Html;
+/// let document = Html::parse_document("<ol class=\"work index group\">...</ol>");
+/// let works = extract_user_works(&document).unwrap();
+/// for work in works.items.into_iter().flatten() {
+///     let accounts: Vec<_> = work.creators.iter().map(|pseud| &pseud.user).collect();
+///     println!("{} by {:?}", work.metadata.name, accounts);
+/// }
+/// ```
+#[inline(always)]
+pub fn extract_user_works(document: &Html) -> Result<UserListing<UserWork>, Ao3ApiError> {
+    extract_user_works_with_base(document, DEFAULT_BASE_URL)
+}
+
+/// Extract a page of a user's works served by a mirror or proxy
+///
+/// # Arguments
+/// * `document` - the parsed works page
+/// * `base_url` - host the page came from, links are resolved against it
+pub fn extract_user_works_with_base(
+    document: &Html,
+    base_url: &str,
+) -> Result<UserListing<UserWork>, Ao3ApiError> {
+    extract_user_work_listing(document, base_url)
+}
+
+/// Extract a page of a user's bookmarks
+///
+/// # Arguments
+/// * `document` - the parsed bookmarks page, e.g. `/users/name/bookmarks`
+///
+/// # Returns
+/// * returns the bookmarks with the bookmarked works, series and external works kept apart from the bookmark records
+#[inline(always)]
+pub fn extract_user_bookmarks(document: &Html) -> Result<UserListing<Bookmark>, Ao3ApiError> {
+    extract_user_bookmarks_with_base(document, DEFAULT_BASE_URL)
+}
+
+/// Extract a page of a user's bookmarks served by a mirror or proxy
+///
+/// # Arguments
+/// * `document` - the parsed bookmarks page
+/// * `base_url` - host the page came from, links are resolved against it
+pub fn extract_user_bookmarks_with_base(
+    document: &Html,
+    base_url: &str,
+) -> Result<UserListing<Bookmark>, Ao3ApiError> {
+    if let Some(gate) = detect_gate(document) {
+        return Err(Ao3ApiError::Gated(gate));
+    }
+    Ok(UserListing {
+        owner: extract_owner(document, base_url)?,
+        info: extract_listing_info(document)?,
+        items: extract_bookmarks_with_base(document, base_url),
+    })
+}
+
+/// Extract a page of works gifted to a user
+///
+/// # Arguments
+/// * `document` - the parsed gifts page, e.g. `/users/name/gifts`
+#[inline(always)]
+pub fn extract_user_gifts(document: &Html) -> Result<UserListing<UserWork>, Ao3ApiError> {
+    extract_user_gifts_with_base(document, DEFAULT_BASE_URL)
+}
+
+/// Extract a page of works gifted to a user served by a mirror or proxy
+///
+/// # Arguments
+/// * `document` - the parsed gifts page
+/// * `base_url` - host the page came from, links are resolved against it
+pub fn extract_user_gifts_with_base(
+    document: &Html,
+    base_url: &str,
+) -> Result<UserListing<UserWork>, Ao3ApiError> {
+    extract_user_work_listing(document, base_url)
+}
+
+/// Helper function to parse one series blurb
+pub(super) fn extract_series_blurb(
+    blurb: ElementRef<'_>,
+    base_url: &str,
+) -> Result<SeriesBlurb, Ao3ApiError> {
+    let link = blurb
+        .select(&safe_static_selector(
+            SERIES_BLURB_LINK_SELECTOR.clone(),
+            SERIES_BLURB_LINK_SELECTOR_TEXT,
+        )?)
+        .next()
+        .ok_or(Ao3ApiError::SelectorError(
+            "Failed to select link from series blurb".to_string(),
+        ))?;
+    let href = link.attr("href").ok_or(Ao3ApiError::SelectorError(
+        "Failed to get href attribute from series link".to_string(),
+    ))?;
+    let id = safe_static_regex(SERIES_ID_REGEX.clone(), SERIES_ID_REGEX_TEXT)?
+        .captures(href)
+        .and_then(|cap| cap.get(1))
+        .map(|id| id.as_str().to_string())
+        .ok_or(Ao3ApiError::RegexError(
+            "Failed to capture id from series url".to_string(),
+        ))?;
+
+    let first_text =
+        |selector: &Option<Selector>, backup: &str| -> Result<Option<String>, Ao3ApiError> {
+            Ok(blurb
+                .select(&safe_static_selector(selector.clone(), backup)?)
+                .next()
+                .map(|elem| elem.text().collect::<String>().trim().to_string()))
+        };
+    let stat = |selector: &Option<Selector>, backup: &str| -> Result<Option<u32>, Ao3ApiError> {
+        Ok(first_text(selector, backup)?.and_then(|text| parse_number_with_commas(&text).ok()))
+    };
+
+    let complete = blurb
+        .select(&safe_static_selector(
+            SERIES_COMPLETE_SELECTOR.clone(),
+            SERIES_COMPLETE_SELECTOR_TEXT,
+        )?)
+        .next()
+        .and_then(|span| {
+            let classes: Vec<&str> = span.value().classes().collect();
+            if classes.contains(&"complete-yes") {
+                Some(true)
+            } else if classes.contains(&"complete-no") {
+                Some(false)
+            } else {
+                None
+            }
+        });
+
+    Ok(SeriesBlurb {
+        url: join_url(base_url, href),
+        id,
+        title: link.text().collect::<String>().trim().to_string(),
+        creators: creators_of(
+            blurb,
+            &safe_static_selector(CREATOR_SELECTOR.clone(), CREATOR_SELECTOR_TEXT)?,
+            base_url,
+        ),
+        fandoms: select_text!(
+            blurb,
+            &safe_static_selector(SERIES_FANDOM_SELECTOR.clone(), SERIES_FANDOM_SELECTOR_TEXT)?
+        ),
+        updated: first_text(&SERIES_DATETIME_SELECTOR, SERIES_DATETIME_SELECTOR_TEXT)?,
+        description: blurb
+            .select(&safe_static_selector(
+                SERIES_SUMMARY_SELECTOR.clone(),
+                SERIES_SUMMARY_SELECTOR_TEXT,
+            )?)
+            .next()
+            .map(html_to_text)
+            .filter(|description| !description.is_empty()),
+        words: stat(&SERIES_WORDS_SELECTOR, SERIES_WORDS_SELECTOR_TEXT)?,
+        works_count: stat(&SERIES_WORKS_SELECTOR, SERIES_WORKS_SELECTOR_TEXT)?,
+        bookmarks: stat(&SERIES_BOOKMARKS_SELECTOR, SERIES_BOOKMARKS_SELECTOR_TEXT)?,
+        complete,
+    })
+}
+
+/// Extract a page of a user's series
+///
+/// # Arguments
+/// * `document` - the parsed series page, e.g. `/users/name/series`
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::extraction::extract_user_series;
+/// use scraper::Html;
+/// let document = Html::parse_document("<ul class=\"series index group\">...</ul>");
+/// for series in extract_user_series(&document).unwrap().items.into_iter().flatten() {
+///     println!("{} ({:?} works)", series.title, series.works_count);
+/// }
+/// ```
+#[inline(always)]
+pub fn extract_user_series(document: &Html) -> Result<UserListing<SeriesBlurb>, Ao3ApiError> {
+    extract_user_series_with_base(document, DEFAULT_BASE_URL)
+}
+
+/// Extract a page of a user's series served by a mirror or proxy
+///
+/// # Arguments
+/// * `document` - the parsed series page
+/// * `base_url` - host the page came from, links are resolved against it
+pub fn extract_user_series_with_base(
+    document: &Html,
+    base_url: &str,
+) -> Result<UserListing<SeriesBlurb>, Ao3ApiError> {
+    if let Some(gate) = detect_gate(document) {
+        return Err(Ao3ApiError::Gated(gate));
+    }
+    let items = document
+        .select(&safe_static_selector(
+            SERIES_BLURB_SELECTOR.clone(),
+            SERIES_BLURB_SELECTOR_TEXT,
+        )?)
+        .map(|blurb| extract_series_blurb(blurb, base_url))
+        .collect();
+    Ok(UserListing {
+        owner: extract_owner(document, base_url)?,
+        info: extract_listing_info(document)?,
+        items,
+    })
+}
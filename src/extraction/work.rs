@@ -0,0 +1,343 @@
+//! Extraction for full work pages (`/works/{id}`)
+//!
+//! Unlike the search-result blurbs handled by [`super::extract_fic_metadata`],
+//! a work page keeps its metadata in `dl.work.meta` and its title, byline,
+//! summary and notes in the work's `div.preface`.
+use super::tags::tag_from_link;
+use super::{
+    SeriesMembership, Tag, TagCategory, detect_gate, extract_series_memberships_with_base,
+    parse_number_with_commas, tags_to_map,
+};
+use crate::errors::Ao3ApiError;
+use crate::networking::config::DEFAULT_BASE_URL;
+use crate::utils::{join_url, make_selector, safe_static_regex, safe_static_selector};
+use crate::{
+    define_regex, define_selector, make_static, select_raw_text, select_raw_text_next, select_text,
+};
+use ficdata::FicMetadata;
+use regex::Regex;
+use scraper::{Html, selector::Selector};
+use std::sync::LazyLock;
+
+define_regex!(WORK_ID_REGEX, WORK_ID_REGEX_TEXT, r#"/works/(\d+)"#);
+define_selector!(
+    KUDO_ID_SELECTOR,
+    KUDO_ID_SELECTOR_TEXT,
+    r#"#kudo_commentable_id"#
+);
+define_selector!(
+    WORK_NAV_LINK_SELECTOR,
+    WORK_NAV_LINK_SELECTOR_TEXT,
+    r#"ul.work.navigation a[href*="/works/"]"#
+);
+define_selector!(
+    TITLE_SELECTOR,
+    TITLE_SELECTOR_TEXT,
+    r#"#workskin > div.preface h2.title"#
+);
+define_selector!(
+    BYLINE_SELECTOR,
+    BYLINE_SELECTOR_TEXT,
+    r#"#workskin > div.preface h3.byline a[rel="author"]"#
+);
+define_selector!(
+    SUMMARY_SELECTOR,
+    SUMMARY_SELECTOR_TEXT,
+    r#"#workskin > div.preface div.summary blockquote.userstuff"#
+);
+define_selector!(
+    NOTES_SELECTOR,
+    NOTES_SELECTOR_TEXT,
+    r#"#workskin > div.preface div.notes blockquote.userstuff"#
+);
+define_selector!(
+    END_NOTES_SELECTOR,
+    END_NOTES_SELECTOR_TEXT,
+    r#"#work_endnotes blockquote.userstuff"#
+);
+define_selector!(
+    META_TAGS_SELECTOR,
+    META_TAGS_SELECTOR_TEXT,
+    r#"dl.work.meta dd.tags"#
+);
+define_selector!(TAG_LINK_SELECTOR, TAG_LINK_SELECTOR_TEXT, r#"a.tag"#);
+define_selector!(
+    CATEGORY_SELECTOR,
+    CATEGORY_SELECTOR_TEXT,
+    r#"dl.work.meta dd.category a.tag"#
+);
+define_selector!(
+    FANDOM_SELECTOR,
+    FANDOM_SELECTOR_TEXT,
+    r#"dl.work.meta dd.fandom a.tag"#
+);
+define_selector!(
+    LANGUAGE_SELECTOR,
+    LANGUAGE_SELECTOR_TEXT,
+    r#"dl.work.meta dd.language"#
+);
+define_selector!(
+    PUBLISHED_SELECTOR,
+    PUBLISHED_SELECTOR_TEXT,
+    r#"dl.stats dd.published"#
+);
+define_selector!(
+    STATUS_LABEL_SELECTOR,
+    STATUS_LABEL_SELECTOR_TEXT,
+    r#"dl.stats dt.status"#
+);
+define_selector!(
+    STATUS_SELECTOR,
+    STATUS_SELECTOR_TEXT,
+    r#"dl.stats dd.status"#
+);
+define_selector!(WORDS_SELECTOR, WORDS_SELECTOR_TEXT, r#"dl.stats dd.words"#);
+define_selector!(
+    CHAPTERS_SELECTOR,
+    CHAPTERS_SELECTOR_TEXT,
+    r#"dl.stats dd.chapters"#
+);
+define_selector!(
+    COMMENTS_SELECTOR,
+    COMMENTS_SELECTOR_TEXT,
+    r#"dl.stats dd.comments"#
+);
+define_selector!(KUDOS_SELECTOR, KUDOS_SELECTOR_TEXT, r#"dl.stats dd.kudos"#);
+define_selector!(
+    BOOKMARKS_SELECTOR,
+    BOOKMARKS_SELECTOR_TEXT,
+    r#"dl.stats dd.bookmarks"#
+);
+define_selector!(HITS_SELECTOR, HITS_SELECTOR_TEXT, r#"dl.stats dd.hits"#);
+
+/// Metadata extracted from a full work page
+///
+/// `metadata` holds everything a search blurb would also give us, so work
+/// pages and blurbs can be stored side by side.
+#[derive(Debug, Clone)]
+pub struct WorkPage {
+    pub metadata: FicMetadata,
+    /// Date the work was first published (`YYYY-MM-DD`)
+    pub published: Option<String>,
+    /// Date of the last update, only set while the work is in progress
+    pub updated: Option<String>,
+    /// Date the work was completed, only set once it is complete
+    pub completed: Option<String>,
+    pub bookmarks: Option<u32>,
+    pub comments: Option<u32>,
+    /// Series the work is part of, also stored as text in `metadata.series`
+    pub series: Vec<SeriesMembership>,
+    /// Tags in page order with the slugs AO3 links them under, also in `metadata.tags`
+    pub tags: Vec<Tag>,
+    /// Beginning notes of the work
+    pub notes: Option<String>,
+    /// End notes of the work
+    pub end_notes: Option<String>,
+}
+
+/// Gets all tags from the `dl.work.meta` block of a work page
+fn extract_work_tags(document: &Html) -> Result<Vec<Tag>, Ao3ApiError> {
+    let mut tags = Vec::new();
+    let link_selector = safe_static_selector(TAG_LINK_SELECTOR.clone(), TAG_LINK_SELECTOR_TEXT)?;
+
+    for dd in document.select(&safe_static_selector(
+        META_TAGS_SELECTOR.clone(),
+        META_TAGS_SELECTOR_TEXT,
+    )?) {
+        // classes look like "freeform tags", keyed the same way blurb tags are
+        let Some(category) = dd.attr("class").and_then(TagCategory::from_key) else {
+            continue;
+        };
+        tags.extend(
+            dd.select(&link_selector)
+                .filter_map(|link| tag_from_link(category, link)),
+        );
+    }
+
+    Ok(tags)
+}
+
+/// Helper function to pull the work id out of a work page
+fn extract_work_id(document: &Html) -> Result<String, Ao3ApiError> {
+    // the kudos form carries the id directly
+    if let Some(id) = document
+        .select(&safe_static_selector(
+            KUDO_ID_SELECTOR.clone(),
+            KUDO_ID_SELECTOR_TEXT,
+        )?)
+        .next()
+        .and_then(|elem| elem.attr("value"))
+    {
+        return Ok(id.to_string());
+    }
+
+    // fall back to the work navigation links
+    let regex = safe_static_regex(WORK_ID_REGEX.clone(), WORK_ID_REGEX_TEXT)?;
+    document
+        .select(&safe_static_selector(
+            WORK_NAV_LINK_SELECTOR.clone(),
+            WORK_NAV_LINK_SELECTOR_TEXT,
+        )?)
+        .filter_map(|elem| elem.attr("href"))
+        .find_map(|href| regex.captures(href))
+        .and_then(|cap| cap.get(1))
+        .map(|id| id.as_str().to_string())
+        .ok_or(Ao3ApiError::SelectorError(
+            "Failed to find work id on work page".to_string(),
+        ))
+}
+
+/// Helper function to select a stat and parse it as a number
+fn extract_stat(
+    document: &Html,
+    selector: &Option<Selector>,
+    backup: &str,
+) -> Result<Option<u32>, Ao3ApiError> {
+    Ok(
+        select_raw_text_next!(document, &safe_static_selector(selector.clone(), backup)?)
+            .and_then(|text| parse_number_with_commas(text.trim()).ok()),
+    )
+}
+
+/// Extract work metadata from a full work page
+///
+/// # Arguments
+/// * `document` - the parsed work page, e.g. `/works/123?view_full_work=true`
+///
+/// # Returns
+/// * returns a [`WorkPage`] with the blurb-level metadata plus dates, stats and notes
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::extraction::extract_work_page;
+/// use scraper::Html;
+/// let document = Html::parse_document("<div id=\"workskin\">...</div>");
+/// if let Ok(work) = extract_work_page(&document) {
+///     println!("{} published {:?}", work.metadata.name, work.published);
+/// }
+/// ```
+#[inline(always)]
+pub fn extract_work_page(document: &Html) -> Result<WorkPage, Ao3ApiError> {
+    extract_work_page_with_base(document, DEFAULT_BASE_URL)
+}
+
+/// Extract work metadata from a work page served by a mirror or proxy
+///
+/// # Arguments
+/// * `document` - the parsed work page
+/// * `base_url` - host the page came from, used to build the work's URL
+pub fn extract_work_page_with_base(
+    document: &Html,
+    base_url: &str,
+) -> Result<WorkPage, Ao3ApiError> {
+    if let Some(gate) = detect_gate(document) {
+        return Err(Ao3ApiError::Gated(gate));
+    }
+    let id = extract_work_id(document)?;
+    let url = join_url(base_url, &format!("/works/{}", id));
+
+    let name = select_raw_text_next!(
+        document,
+        &safe_static_selector(TITLE_SELECTOR.clone(), TITLE_SELECTOR_TEXT)?
+    )
+    .ok_or(Ao3ApiError::SelectorError(
+        "Failed to select title from work preface".to_string(),
+    ))?;
+
+    let authors: Vec<String> = select_text!(
+        document,
+        &safe_static_selector(BYLINE_SELECTOR.clone(), BYLINE_SELECTOR_TEXT)?
+    );
+
+    let description = select_raw_text_next!(
+        document,
+        &safe_static_selector(SUMMARY_SELECTOR.clone(), SUMMARY_SELECTOR_TEXT)?
+    )
+    .unwrap_or_default();
+    let notes = select_raw_text_next!(
+        document,
+        &safe_static_selector(NOTES_SELECTOR.clone(), NOTES_SELECTOR_TEXT)?
+    );
+    let end_notes = select_raw_text_next!(
+        document,
+        &safe_static_selector(END_NOTES_SELECTOR.clone(), END_NOTES_SELECTOR_TEXT)?
+    );
+
+    let fandom: Vec<String> = select_text!(
+        document,
+        &safe_static_selector(FANDOM_SELECTOR.clone(), FANDOM_SELECTOR_TEXT)?
+    );
+    let ship_type: Vec<String> = select_text!(
+        document,
+        &safe_static_selector(CATEGORY_SELECTOR.clone(), CATEGORY_SELECTOR_TEXT)?
+    );
+    let language = select_raw_text_next!(
+        document,
+        &safe_static_selector(LANGUAGE_SELECTOR.clone(), LANGUAGE_SELECTOR_TEXT)?
+    );
+    let chapters = select_raw_text_next!(
+        document,
+        &safe_static_selector(CHAPTERS_SELECTOR.clone(), CHAPTERS_SELECTOR_TEXT)?
+    );
+    // "Part 2 of the Series Name series", stored as "Part 2 of Series Name" to match blurbs
+    let series = extract_series_memberships_with_base(document, base_url)?;
+
+    // Dates: dd.status is either "Updated:" or "Completed:" depending on dt.status
+    let published = select_raw_text_next!(
+        document,
+        &safe_static_selector(PUBLISHED_SELECTOR.clone(), PUBLISHED_SELECTOR_TEXT)?
+    );
+    let status = select_raw_text_next!(
+        document,
+        &safe_static_selector(STATUS_SELECTOR.clone(), STATUS_SELECTOR_TEXT)?
+    );
+    let is_completed = select_raw_text_next!(
+        document,
+        &safe_static_selector(STATUS_LABEL_SELECTOR.clone(), STATUS_LABEL_SELECTOR_TEXT)?
+    )
+    .is_some_and(|label| label.starts_with("Completed"));
+    let (updated, completed) = if is_completed {
+        (None, status)
+    } else {
+        (status, None)
+    };
+    let last_updated = completed
+        .clone()
+        .or_else(|| updated.clone())
+        .or_else(|| published.clone())
+        .unwrap_or_else(|| "Unknown".to_string());
+
+    let words = extract_stat(document, &WORDS_SELECTOR, WORDS_SELECTOR_TEXT)?;
+    let kudos = extract_stat(document, &KUDOS_SELECTOR, KUDOS_SELECTOR_TEXT)?;
+    let hits = extract_stat(document, &HITS_SELECTOR, HITS_SELECTOR_TEXT)?;
+    let bookmarks = extract_stat(document, &BOOKMARKS_SELECTOR, BOOKMARKS_SELECTOR_TEXT)?;
+    let comments = extract_stat(document, &COMMENTS_SELECTOR, COMMENTS_SELECTOR_TEXT)?;
+
+    let tags = extract_work_tags(document).unwrap_or_default();
+
+    let metadata = FicMetadata::new(id, name, url, last_updated)
+        .with_tags(tags_to_map(&tags))
+        .with_description(description)
+        .with_authors(authors)
+        .with_fandom(fandom)
+        .with_ship_type(ship_type)
+        .with_language(language)
+        .with_chapters(chapters)
+        .with_kudos(Some(kudos.unwrap_or(0)))
+        .with_words(words)
+        .with_series(series.iter().map(ToString::to_string).collect())
+        .with_hits(hits);
+
+    Ok(WorkPage {
+        metadata,
+        published,
+        updated,
+        completed,
+        bookmarks,
+        comments,
+        series,
+        tags,
+        notes,
+        end_notes,
+    })
+}
@@ -1,4 +1,10 @@
-mod errors;
+pub mod download;
+pub mod epub;
+pub mod errors;
 pub mod extraction;
 pub mod networking;
+pub mod search;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod utils;
+pub use errors::Ao3ApiError;
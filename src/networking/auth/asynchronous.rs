@@ -0,0 +1,231 @@
+//! Async authentication implementation for AO3
+use crate::errors::Ao3ApiError;
+use crate::networking::auth::session::{
+    CREDENTIALS_COOKIE, Session, extract_form_token, extract_session, redirect_target, verify_login,
+};
+use crate::networking::auth::{CredentialSource, Token};
+use crate::networking::client::asynchronous::Ao3Client;
+use crate::networking::client::asynchronous::get_page;
+use crate::networking::retry::{give_up, parse_retry_after};
+use log::{debug, warn};
+use reqwest::header::RETRY_AFTER;
+use scraper::Html;
+use tokio::task::spawn_blocking;
+
+/// Get an auth token for the client's session
+///
+/// # Arguments
+/// * `client` - async reqwest Client being used
+///
+/// # Returns
+/// * Returns an auth token as String
+///
+/// # Example
+/// ```no_run
+/// # async fn run() {
+/// use ao3_api_rs::networking::client::asynchronous::create_client;
+/// use ao3_api_rs::networking::auth::asynchronous::get_token;
+/// let client = create_client("test").unwrap();
+/// let token = get_token(&client).await;
+/// # }
+/// ```
+pub async fn get_token(client: &Ao3Client) -> Result<String, Ao3ApiError> {
+    let temp = get_page(&client.config().url("/token_dispenser.json"), client)
+        .await?
+        .text()
+        .await?;
+    let j: Token = serde_json::from_str(&temp)?;
+    debug!("Token is: {}", j.token);
+
+    Ok(j.token)
+}
+
+/// Get an auth token from the login form
+///
+/// Fallback for when `token_dispenser.json` is unavailable.
+///
+/// # Arguments
+/// * `client` - async reqwest Client being used
+pub async fn get_form_token(client: &Ao3Client) -> Result<String, Ao3ApiError> {
+    let url = client.config().url("/users/login");
+    let body = get_page(&url, client).await?.text().await?;
+    extract_form_token(&Html::parse_document(&body)).ok_or(Ao3ApiError::Parse {
+        what: "authenticity_token".to_string(),
+        url,
+    })
+}
+
+/// Login to AO3 with credentials from a `CredentialSource`
+///
+/// # Arguments
+/// * `client` - async reqwest Client with cookie store enabled
+/// * `credentials` - where to get the username and password, a `&str` is read as a login file
+///   (username on first line, password on second)
+///
+/// # Returns
+/// * Returns the logged in `Session`, or `AuthFailed` with AO3's message when the login was rejected
+///
+/// # Example
+/// ```no_run
+/// # async fn run() {
+/// use ao3_api_rs::networking::client::asynchronous::create_client;
+/// use ao3_api_rs::networking::auth::asynchronous::login;
+/// let client = create_client("test").unwrap();
+/// let session = login(&client, "log.txt").await.expect("Failed to log in");
+/// # }
+/// ```
+pub async fn login(
+    client: &Ao3Client,
+    credentials: impl Into<CredentialSource>,
+) -> Result<Session, Ao3ApiError> {
+    // get the auth token, the login form has one too when the dispenser is down
+    let token = match get_token(client).await {
+        Ok(token) => token,
+        Err(err) => {
+            warn!(
+                "Token dispenser failed ({}), using the login form instead",
+                err
+            );
+            get_form_token(client).await?
+        }
+    };
+    // resolve the credentials, the password is wiped when info is dropped.
+    // reading a file or the terminal blocks, so it runs off the async workers
+    let credentials = credentials.into();
+    let info = spawn_blocking(move || credentials.resolve())
+        .await
+        .map_err(|err| Ao3ApiError::GenericError(format!("credential lookup failed: {}", err)))??;
+    // reqwest percent-encodes the form, the token and password may contain + / & = %
+    let form = [
+        ("authenticity_token", token.as_str()),
+        ("user[login]", &info.username),
+        ("user[password]", &info.password),
+        ("commit", "Log In"),
+    ];
+    // set the post request to log in
+    client.throttle().await;
+    let response = client
+        .inner()
+        .post(client.config().url("/users/login"))
+        .form(&form)
+        .send()
+        .await?;
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        return Err(give_up(status, response.url().to_string(), retry_after));
+    }
+    let remembered = response
+        .cookies()
+        .any(|cookie| cookie.name() == CREDENTIALS_COOKIE);
+    // AO3 redirects to the dashboard on success and back to the form on failure
+    let body = match redirect_target(status, response.url(), response.headers()) {
+        Some(target) => match get_page(target.as_str(), client).await {
+            Ok(page) => page.text().await?,
+            Err(Ao3ApiError::LoginRequired { .. }) => {
+                return Err(Ao3ApiError::AuthFailed(
+                    "AO3 sent us back to the login page".to_string(),
+                ));
+            }
+            Err(err) => return Err(err),
+        },
+        None => response.text().await?,
+    };
+    let session = verify_login(&Html::parse_document(&body), remembered, &info.username)?;
+    debug!("Logged in as {}", session.username);
+    Ok(session)
+}
+
+/// Get the session the client's cookies belong to
+///
+/// # Arguments
+/// * `client` - async reqwest Client with cookie store enabled
+///
+/// # Returns
+/// * Returns the `Session`, or `None` when AO3 treats the client as a guest
+pub async fn current_session(client: &Ao3Client) -> Result<Option<Session>, Ao3ApiError> {
+    let body = get_page(&client.config().url("/"), client)
+        .await?
+        .text()
+        .await?;
+    Ok(extract_session(&Html::parse_document(&body)))
+}
+
+/// Check whether the client is logged in
+///
+/// # Arguments
+/// * `client` - async reqwest Client with cookie store enabled
+#[inline(always)]
+pub async fn is_logged_in(client: &Ao3Client) -> Result<bool, Ao3ApiError> {
+    Ok(current_session(client).await?.is_some())
+}
+
+/// Restore the session from the client's cookies, logging in again when it went stale
+///
+/// Cookies are loaded from `ClientConfig::cookie_file` when the client is built.
+/// After a fresh login they are saved back to it.
+///
+/// # Arguments
+/// * `client` - async reqwest Client with cookie store enabled
+/// * `credentials` - used only when there is no usable saved session
+///
+/// # Example
+/// ```no_run
+/// # async fn run() {
+/// use ao3_api_rs::networking::ClientConfig;
+/// use ao3_api_rs::networking::client::asynchronous::Ao3Client;
+/// use ao3_api_rs::networking::auth::asynchronous::resume_session;
+/// let config = ClientConfig::new("test").with_cookie_file("cookies.json");
+/// let client = Ao3Client::new(config).unwrap();
+/// let session = resume_session(&client, "log.txt").await.expect("Failed to log in");
+/// # }
+/// ```
+pub async fn resume_session(
+    client: &Ao3Client,
+    credentials: impl Into<CredentialSource>,
+) -> Result<Session, Ao3ApiError> {
+    if client.cookies().has_session(&client.config().url("/")) {
+        if let Some(session) = current_session(client).await? {
+            debug!("Restored session for {}", session.username);
+            return Ok(session);
+        }
+        debug!("Saved session is stale, logging in again");
+    }
+    client.cookies().clear();
+    let session = login(client, credentials).await?;
+    if client.config().cookie_file.is_some() {
+        client.save_cookies()?;
+    }
+    Ok(session)
+}
+
+/// Log the client out of AO3
+///
+/// # Arguments
+/// * `client` - async reqwest Client with cookie store enabled
+pub async fn logout(client: &Ao3Client) -> Result<(), Ao3ApiError> {
+    let token = get_token(client).await?;
+    client.throttle().await;
+    let response = client
+        .inner()
+        .post(client.config().url("/users/logout"))
+        .form(&[
+            ("_method", "delete"),
+            ("authenticity_token", token.as_str()),
+        ])
+        .send()
+        .await?;
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        return Err(Ao3ApiError::Http {
+            status,
+            url: response.url().to_string(),
+        });
+    }
+    debug!("Logged out");
+    Ok(())
+}
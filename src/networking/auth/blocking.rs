@@ -1,18 +1,17 @@
 //! Blocking authentication implementation for AO3
+use crate::errors::Ao3ApiError;
+use crate::networking::Ao3Client;
+use crate::networking::auth::CredentialSource;
+pub use crate::networking::auth::credentials::{LoginInfo, get_login_info};
+use crate::networking::auth::session::{
+    CREDENTIALS_COOKIE, Session, extract_form_token, extract_session, redirect_target, verify_login,
+};
 use crate::networking::get_page;
-use log::debug;
-use reqwest::blocking::Client;
+use crate::networking::retry::{give_up, parse_retry_after};
+use log::{debug, warn};
+use reqwest::header::RETRY_AFTER;
+use scraper::Html;
 use serde::Deserialize;
-use std::fs;
-use std::thread::sleep;
-use std::time::Duration;
-
-/// Login information for AO3 authentication
-#[derive(Debug, Clone)]
-pub struct LoginInfo {
-    pub username: Box<str>,
-    pub password: Box<str>,
-}
 
 /// Authentication token from AO3
 #[derive(Deserialize, Debug)]
@@ -20,83 +19,215 @@ pub struct Token {
     pub token: String,
 }
 
-/// Get login information from text file at provided path
+/// Get an auth token for the client's session
 ///
 /// # Arguments
-/// * `path` - Path to login file (username on first line, password on second)
+/// * `client` - reqwest Client being used
 ///
 /// # Returns
-/// * Returns LoginInfo struct with username and password
+/// * Returns an auth token as String
 ///
 /// # Example
 /// ```no_run
-/// use ao3_api_rs::networking::get_login_info;
-/// let info = get_login_info("log.txt");
+/// use ao3_api_rs::networking::{create_client, get_token};
+/// let client = create_client("test").unwrap();
+/// let token = get_token(&client);
 /// ```
-#[inline(always)]
-pub fn get_login_info(path: &str) -> LoginInfo {
-    let file = fs::read_to_string(path).expect("failed to read login file");
-    let mut lines = file.lines();
-    LoginInfo {
-        username: lines.next().expect("Username not found").to_owned().into(),
-        password: lines.next().expect("Password not found").to_owned().into(),
-    }
+pub fn get_token(client: &Ao3Client) -> Result<String, Ao3ApiError> {
+    let temp = get_page(&client.config().url("/token_dispenser.json"), client)?.text()?;
+    let j: Token = serde_json::from_str(&temp)?;
+    debug!("Token is: {}", j.token);
+
+    Ok(j.token)
 }
 
-/// Get an auth token for the client's session
+/// Get an auth token from the login form
+///
+/// Fallback for when `token_dispenser.json` is unavailable.
 ///
 /// # Arguments
 /// * `client` - reqwest Client being used
 ///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::networking::{create_client, get_form_token};
+/// let client = create_client("test").unwrap();
+/// let token = get_form_token(&client);
+/// ```
+pub fn get_form_token(client: &Ao3Client) -> Result<String, Ao3ApiError> {
+    let url = client.config().url("/users/login");
+    let body = get_page(&url, client)?.text()?;
+    extract_form_token(&Html::parse_document(&body)).ok_or(Ao3ApiError::Parse {
+        what: "authenticity_token".to_string(),
+        url,
+    })
+}
+
+/// Login to AO3 with credentials from a `CredentialSource`
+///
+/// # Arguments
+/// * `client` - reqwest Client with cookie store enabled
+/// * `credentials` - where to get the username and password, a `&str` is read as a login file
+///   (username on first line, password on second)
+///
 /// # Returns
-/// * Returns an auth token as String
+/// * Returns the logged in `Session`, or `AuthFailed` with AO3's message when the login was rejected
 ///
 /// # Example
 /// ```no_run
-/// use ao3_api_rs::networking::{create_client, get_token};
+/// use ao3_api_rs::networking::{create_client, login};
 /// let client = create_client("test").unwrap();
-/// let token = get_token(&client);
+/// let session = login(&client, "log.txt").expect("Failed to log in");
+/// println!("logged in as {}", session.username);
 /// ```
-pub fn get_token(client: &Client) -> String {
-    let temp = get_page("https://archiveofourown.org/token_dispenser.json", client)
-        .unwrap()
-        .text()
-        .unwrap();
-    let j: Token = serde_json::from_str(&temp).unwrap();
-    debug!("Token is: {}", j.token);
+pub fn login(
+    client: &Ao3Client,
+    credentials: impl Into<CredentialSource>,
+) -> Result<Session, Ao3ApiError> {
+    // get the auth token, the login form has one too when the dispenser is down
+    let token = match get_token(client) {
+        Ok(token) => token,
+        Err(err) => {
+            warn!(
+                "Token dispenser failed ({}), using the login form instead",
+                err
+            );
+            get_form_token(client)?
+        }
+    };
+    // resolve the credentials, the password is wiped when info is dropped
+    let info = credentials.into().resolve()?;
+    // reqwest percent-encodes the form, the token and password may contain + / & = %
+    let form = [
+        ("authenticity_token", token.as_str()),
+        ("user[login]", &info.username),
+        ("user[password]", &info.password),
+        ("commit", "Log In"),
+    ];
+    // set the post request to log in
+    client.throttle();
+    let response = client
+        .inner()
+        .post(client.config().url("/users/login"))
+        .form(&form)
+        .send()?;
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        return Err(give_up(status, response.url().to_string(), retry_after));
+    }
+    let remembered = response
+        .cookies()
+        .any(|cookie| cookie.name() == CREDENTIALS_COOKIE);
+    // AO3 redirects to the dashboard on success and back to the form on failure
+    let body = match redirect_target(status, response.url(), response.headers()) {
+        Some(target) => match get_page(target.as_str(), client) {
+            Ok(page) => page.text()?,
+            Err(Ao3ApiError::LoginRequired { .. }) => {
+                return Err(Ao3ApiError::AuthFailed(
+                    "AO3 sent us back to the login page".to_string(),
+                ));
+            }
+            Err(err) => return Err(err),
+        },
+        None => response.text()?,
+    };
+    let session = verify_login(&Html::parse_document(&body), remembered, &info.username)?;
+    debug!("Logged in as {}", session.username);
+    Ok(session)
+}
 
-    j.token.to_string()
+/// Get the session the client's cookies belong to
+///
+/// # Arguments
+/// * `client` - reqwest Client with cookie store enabled
+///
+/// # Returns
+/// * Returns the `Session`, or `None` when AO3 treats the client as a guest
+pub fn current_session(client: &Ao3Client) -> Result<Option<Session>, Ao3ApiError> {
+    let body = get_page(&client.config().url("/"), client)?.text()?;
+    Ok(extract_session(&Html::parse_document(&body)))
 }
 
-/// Login to AO3 with credentials from a file
+/// Check whether the client is logged in
 ///
 /// # Arguments
 /// * `client` - reqwest Client with cookie store enabled
-/// * `login_file` - Path to login file (username on first line, password on second)
 ///
 /// # Example
 /// ```no_run
-/// use ao3_api_rs::networking::{create_client, login};
+/// use ao3_api_rs::networking::{create_client, is_logged_in, login};
 /// let client = create_client("test").unwrap();
-/// login(&client, "log.txt");
+/// if !is_logged_in(&client).unwrap() {
+///     login(&client, "log.txt").expect("Failed to log in");
+/// }
 /// ```
-pub fn login(client: &Client, login_file: &str) {
-    // get the auth token
-    let token = get_token(client);
-    sleep(Duration::from_secs(2));
-    // we get login information from the file
-    let info = get_login_info(login_file);
-    // create the request body using format! for better performance
-    let loginbody = format!(
-        "authenticity_token={}&user%5Blogin%5D={}&user%5Bpassword%5D={}&commit=Log+In",
-        token, info.username, info.password
-    );
-    // set the post request to log in
-    let _page = client
-        .post("https://archiveofourown.org/users/login")
-        .body(loginbody)
-        .send()
-        .expect("Failed to send login request");
-    sleep(Duration::from_secs(2));
-    println!("logged in");
+#[inline(always)]
+pub fn is_logged_in(client: &Ao3Client) -> Result<bool, Ao3ApiError> {
+    Ok(current_session(client)?.is_some())
+}
+
+/// Restore the session from the client's cookies, logging in again when it went stale
+///
+/// Cookies are loaded from `ClientConfig::cookie_file` when the client is built.
+/// After a fresh login they are saved back to it.
+///
+/// # Arguments
+/// * `client` - reqwest Client with cookie store enabled
+/// * `credentials` - used only when there is no usable saved session
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::networking::{Ao3Client, ClientConfig, resume_session};
+/// let config = ClientConfig::new("test").with_cookie_file("cookies.json");
+/// let client = Ao3Client::new(config).unwrap();
+/// let session = resume_session(&client, "log.txt").expect("Failed to log in");
+/// ```
+pub fn resume_session(
+    client: &Ao3Client,
+    credentials: impl Into<CredentialSource>,
+) -> Result<Session, Ao3ApiError> {
+    if client.cookies().has_session(&client.config().url("/")) {
+        if let Some(session) = current_session(client)? {
+            debug!("Restored session for {}", session.username);
+            return Ok(session);
+        }
+        debug!("Saved session is stale, logging in again");
+    }
+    client.cookies().clear();
+    let session = login(client, credentials)?;
+    if client.config().cookie_file.is_some() {
+        client.save_cookies()?;
+    }
+    Ok(session)
+}
+
+/// Log the client out of AO3
+///
+/// # Arguments
+/// * `client` - reqwest Client with cookie store enabled
+pub fn logout(client: &Ao3Client) -> Result<(), Ao3ApiError> {
+    let token = get_token(client)?;
+    client.throttle();
+    let response = client
+        .inner()
+        .post(client.config().url("/users/logout"))
+        .form(&[
+            ("_method", "delete"),
+            ("authenticity_token", token.as_str()),
+        ])
+        .send()?;
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        return Err(Ao3ApiError::Http {
+            status,
+            url: response.url().to_string(),
+        });
+    }
+    debug!("Logged out");
+    Ok(())
 }
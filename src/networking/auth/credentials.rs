@@ -0,0 +1,184 @@
+//! Sources of AO3 login credentials
+use crate::errors::Ao3ApiError;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Default environment variable for the username
+pub static USERNAME_VAR: &str = "AO3_USERNAME";
+/// Default environment variable for the password
+pub static PASSWORD_VAR: &str = "AO3_PASSWORD";
+
+/// Login information for AO3 authentication
+///
+/// The password is wiped from memory when this is dropped.
+#[derive(Clone)]
+pub struct LoginInfo {
+    pub username: Box<str>,
+    pub password: Box<str>,
+}
+
+impl LoginInfo {
+    pub fn new(username: &str, password: &str) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+}
+
+impl fmt::Debug for LoginInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoginInfo")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+impl Drop for LoginInfo {
+    fn drop(&mut self) {
+        self.password.zeroize();
+    }
+}
+
+/// Where `login` gets the username and password from
+#[derive(Debug, Clone)]
+pub enum CredentialSource {
+    /// Text file with the username on the first line and the password on the second
+    File(PathBuf),
+    /// Like `File`, but refuses files that group or others can access
+    SecretFile(PathBuf),
+    /// Environment variables holding the username and password
+    Env { username: String, password: String },
+    /// Credentials constructed in code
+    Info(LoginInfo),
+    /// Ask on the terminal, the password is read without echo
+    Prompt,
+}
+
+impl CredentialSource {
+    /// Read credentials from `AO3_USERNAME` and `AO3_PASSWORD`
+    pub fn env() -> Self {
+        CredentialSource::Env {
+            username: USERNAME_VAR.to_string(),
+            password: PASSWORD_VAR.to_string(),
+        }
+    }
+
+    /// Get the login information from this source
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ao3_api_rs::networking::auth::CredentialSource;
+    /// let info = CredentialSource::env().resolve().expect("AO3_USERNAME/AO3_PASSWORD not set");
+    /// ```
+    pub fn resolve(self) -> Result<LoginInfo, Ao3ApiError> {
+        match self {
+            CredentialSource::File(path) => read_login_file(&path),
+            CredentialSource::SecretFile(path) => {
+                check_permissions(&path)?;
+                read_login_file(&path)
+            }
+            CredentialSource::Env { username, password } => {
+                let var = |name: &str| {
+                    std::env::var(name).map(Zeroizing::new).map_err(|_| {
+                        Ao3ApiError::AuthFailed(format!("environment variable {} is not set", name))
+                    })
+                };
+                Ok(LoginInfo::new(&var(&username)?, &var(&password)?))
+            }
+            CredentialSource::Info(info) => Ok(info),
+            CredentialSource::Prompt => prompt(),
+        }
+    }
+}
+
+impl From<&str> for CredentialSource {
+    fn from(path: &str) -> Self {
+        CredentialSource::File(path.into())
+    }
+}
+
+impl From<PathBuf> for CredentialSource {
+    fn from(path: PathBuf) -> Self {
+        CredentialSource::File(path)
+    }
+}
+
+impl From<LoginInfo> for CredentialSource {
+    fn from(info: LoginInfo) -> Self {
+        CredentialSource::Info(info)
+    }
+}
+
+/// Get login information from text file at provided path
+///
+/// # Arguments
+/// * `path` - Path to login file (username on first line, password on second)
+///
+/// # Returns
+/// * Returns LoginInfo struct with username and password
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::networking::get_login_info;
+/// let info = get_login_info("log.txt");
+/// ```
+#[inline(always)]
+pub fn get_login_info(path: &str) -> Result<LoginInfo, Ao3ApiError> {
+    read_login_file(Path::new(path))
+}
+
+fn read_login_file(path: &Path) -> Result<LoginInfo, Ao3ApiError> {
+    let file = Zeroizing::new(fs::read_to_string(path)?);
+    let mut lines = file.lines();
+    let mut next_line = |what: &str| {
+        lines.next().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} not found in login file {}", what, path.display()),
+            )
+        })
+    };
+    let username = next_line("Username")?;
+    let password = next_line("Password")?;
+    Ok(LoginInfo::new(username, password))
+}
+
+/// Refuse secret files that anyone but the owner can read or write
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> Result<(), Ao3ApiError> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = fs::metadata(path)?.permissions().mode();
+    if mode & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "secret file {} has mode {:o}, it must only be accessible by its owner (chmod 600)",
+                path.display(),
+                mode & 0o777
+            ),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(path: &Path) -> Result<(), Ao3ApiError> {
+    fs::metadata(path)?;
+    Ok(())
+}
+
+/// Ask for the username on stdin and the password without echo
+fn prompt() -> Result<LoginInfo, Ao3ApiError> {
+    print!("AO3 username: ");
+    io::stdout().flush()?;
+    let mut username = String::new();
+    io::stdin().lock().read_line(&mut username)?;
+    let password = Zeroizing::new(rpassword::prompt_password("AO3 password: ")?);
+    Ok(LoginInfo::new(username.trim(), &password))
+}
@@ -1,4 +1,10 @@
 //! Authentication types and helpers
 
+#[cfg(feature = "async")]
+pub mod asynchronous;
 pub mod blocking;
-pub use blocking::{LoginInfo, Token, get_login_info, get_token};
+pub mod credentials;
+pub mod session;
+pub use blocking::{Token, get_token};
+pub use credentials::{CredentialSource, LoginInfo, get_login_info};
+pub use session::Session;
@@ -0,0 +1,150 @@
+//! Checking whether a page was served to a logged in user
+//!
+//! AO3 answers a login attempt with a redirect whether it worked or not, so
+//! the only reliable signals are on the page it leads to: the "Hi, username!"
+//! greeting for a session, or a flash message explaining the rejection.
+use crate::errors::Ao3ApiError;
+use crate::utils::{make_selector, safe_static_regex, safe_static_selector};
+use crate::{define_regex, define_selector, make_static};
+use regex::Regex;
+use reqwest::header::{HeaderMap, LOCATION};
+use reqwest::{StatusCode, Url};
+use scraper::{Html, selector::Selector};
+use std::sync::LazyLock;
+
+define_selector!(
+    GREETING_SELECTOR,
+    GREETING_SELECTOR_TEXT,
+    r#"#greeting a.dropdown-toggle, #greeting li.dropdown > a"#
+);
+define_selector!(
+    FLASH_ERROR_SELECTOR,
+    FLASH_ERROR_SELECTOR_TEXT,
+    r#"div.flash.error, div.flash.alert, div#error"#
+);
+define_selector!(
+    FORM_TOKEN_SELECTOR,
+    FORM_TOKEN_SELECTOR_TEXT,
+    r#"form#new_user input[name="authenticity_token"]"#
+);
+define_selector!(
+    CSRF_META_SELECTOR,
+    CSRF_META_SELECTOR_TEXT,
+    r#"meta[name="csrf-token"]"#
+);
+define_regex!(USER_LINK_REGEX, USER_LINK_REGEX_TEXT, r#"/users/([^/?#]+)"#);
+define_regex!(GREETING_REGEX, GREETING_REGEX_TEXT, r#"Hi,\s*(.+?)!"#);
+
+/// Name of the cookie AO3 sets when a login is remembered
+pub(crate) static CREDENTIALS_COOKIE: &str = "user_credentials";
+
+/// A logged in AO3 session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    /// The account name, not the pseud shown in the greeting
+    pub username: String,
+}
+
+/// Find the logged in user in the header of any AO3 page
+///
+/// # Arguments
+/// * `document` - any parsed AO3 page
+///
+/// # Returns
+/// * returns the session, or `None` when the page was served to a guest
+pub(crate) fn extract_session(document: &Html) -> Option<Session> {
+    let selector = safe_static_selector(GREETING_SELECTOR.clone(), GREETING_SELECTOR_TEXT).ok()?;
+    let greeting = document.select(&selector).next()?;
+    // the link has the account name, the greeting text may show a pseud instead
+    let from_link = greeting.attr("href").and_then(|href| {
+        safe_static_regex(USER_LINK_REGEX.clone(), USER_LINK_REGEX_TEXT)
+            .ok()?
+            .captures(href)?
+            .get(1)
+            .map(|name| name.as_str().to_string())
+    });
+    let username = from_link.or_else(|| {
+        let text = greeting.text().collect::<String>();
+        safe_static_regex(GREETING_REGEX.clone(), GREETING_REGEX_TEXT)
+            .ok()?
+            .captures(&text)?
+            .get(1)
+            .map(|name| name.as_str().trim().to_string())
+    })?;
+    Some(Session { username })
+}
+
+/// Helper function to get the text of AO3's error flash message
+fn extract_flash_error(document: &Html) -> Option<String> {
+    let selector =
+        safe_static_selector(FLASH_ERROR_SELECTOR.clone(), FLASH_ERROR_SELECTOR_TEXT).ok()?;
+    document
+        .select(&selector)
+        .map(|flash| {
+            flash
+                .text()
+                .collect::<String>()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .find(|text| !text.is_empty())
+}
+
+/// Get the `authenticity_token` embedded in the login page
+///
+/// # Arguments
+/// * `document` - the parsed `/users/login` page
+///
+/// # Returns
+/// * returns the token from the login form, or from the csrf meta tag
+pub(crate) fn extract_form_token(document: &Html) -> Option<String> {
+    let form = safe_static_selector(FORM_TOKEN_SELECTOR.clone(), FORM_TOKEN_SELECTOR_TEXT).ok()?;
+    let meta = safe_static_selector(CSRF_META_SELECTOR.clone(), CSRF_META_SELECTOR_TEXT).ok()?;
+    // the form's own token first, the meta tag is shared by every form on the page
+    document
+        .select(&form)
+        .filter_map(|input| input.attr("value"))
+        .chain(document.select(&meta).filter_map(|tag| tag.attr("content")))
+        .find(|token| !token.is_empty())
+        .map(str::to_string)
+}
+
+/// Decide whether the page reached after posting the login form is logged in
+///
+/// # Arguments
+/// * `document` - the page AO3 led to after the login POST
+/// * `remembered` - whether the response set the `user_credentials` cookie
+/// * `username` - the name used to log in, used when the page has no greeting
+///
+/// # Returns
+/// * returns the session, or `AuthFailed` with AO3's message
+pub(crate) fn verify_login(
+    document: &Html,
+    remembered: bool,
+    username: &str,
+) -> Result<Session, Ao3ApiError> {
+    if let Some(session) = extract_session(document) {
+        return Ok(session);
+    }
+    if let Some(message) = extract_flash_error(document) {
+        return Err(Ao3ApiError::AuthFailed(message));
+    }
+    if remembered {
+        return Ok(Session {
+            username: username.to_string(),
+        });
+    }
+    Err(Ao3ApiError::AuthFailed(
+        "AO3 did not confirm the login".to_string(),
+    ))
+}
+
+/// Helper function to resolve where a login or logout response redirects to
+pub(crate) fn redirect_target(status: StatusCode, url: &Url, headers: &HeaderMap) -> Option<Url> {
+    if !status.is_redirection() {
+        return None;
+    }
+    let location = headers.get(LOCATION)?.to_str().ok()?;
+    url.join(location).ok()
+}
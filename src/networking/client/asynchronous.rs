@@ -0,0 +1,268 @@
+//! Async HTTP client implementation for AO3
+//!
+//! Mirrors [`crate::networking::client::blocking`] on top of `reqwest::Client`,
+//! waiting with `tokio::time::sleep` instead of blocking the calling thread.
+
+use crate::errors::Ao3ApiError;
+use crate::extraction::{Gate, detect_gate};
+use crate::networking::client::{Fetch, Step, adult_url, gate_error};
+use crate::networking::config::ClientConfig;
+use crate::networking::cookies::CookieJar;
+use crate::networking::pagination::asynchronous::Pages;
+use crate::networking::ratelimit::RateLimiter;
+use log::debug;
+use reqwest::{self, Client, Response, redirect};
+use scraper::Html;
+use std::sync::Arc;
+use tokio::time::sleep;
+
+/// Async client for AO3
+///
+/// Wraps a reqwest Client together with its configuration and rate limiter.
+/// Clones share the rate limiter, so one client can be used by many tasks
+/// without exceeding the configured request rate.
+#[derive(Debug, Clone)]
+pub struct Ao3Client {
+    client: Client,
+    limiter: Arc<RateLimiter>,
+    config: Arc<ClientConfig>,
+    cookies: Arc<CookieJar>,
+}
+
+impl Ao3Client {
+    /// Build a client from `config`
+    pub fn new(config: ClientConfig) -> Result<Self, Ao3ApiError> {
+        let cookies = Arc::new(match &config.cookie_file {
+            Some(path) => CookieJar::load_or_default(path)?,
+            None => CookieJar::default(),
+        });
+        let client = Client::builder()
+            .redirect(redirect::Policy::none())
+            .cookie_provider(cookies.clone())
+            .timeout(config.timeout)
+            .user_agent(&config.useragent)
+            .build()?;
+        Ok(Self {
+            client,
+            limiter: Arc::new(RateLimiter::new(config.requests_per_minute, config.burst)),
+            config: Arc::new(config),
+            cookies,
+        })
+    }
+
+    /// Share `limiter` with other clients, e.g. a blocking and an async client
+    pub fn with_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// The underlying reqwest Client, requests sent through it skip the rate limiter
+    pub fn inner(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    pub fn limiter(&self) -> &Arc<RateLimiter> {
+        &self.limiter
+    }
+
+    pub fn cookies(&self) -> &Arc<CookieJar> {
+        &self.cookies
+    }
+
+    /// Save the cookies to the configured `cookie_file`
+    pub fn save_cookies(&self) -> Result<(), Ao3ApiError> {
+        match &self.config.cookie_file {
+            Some(path) => self.cookies.save(path),
+            None => Err(Ao3ApiError::GenericError(
+                "no cookie file configured for this client".to_string(),
+            )),
+        }
+    }
+
+    /// Wait until the rate limiter allows another request
+    pub(crate) async fn throttle(&self) {
+        let wait = self.limiter.reserve();
+        if !wait.is_zero() {
+            debug!("Rate limited, waiting {:?}", wait);
+            sleep(wait).await;
+        }
+    }
+}
+
+/// A successful response together with the redirects taken to reach it
+#[derive(Debug)]
+pub struct Page {
+    response: Response,
+    /// URLs that redirected, in the order they were visited
+    pub redirects: Vec<String>,
+}
+
+impl Page {
+    /// Final URL of the page after following redirects
+    pub fn url(&self) -> &reqwest::Url {
+        self.response.url()
+    }
+
+    pub fn status(&self) -> reqwest::StatusCode {
+        self.response.status()
+    }
+
+    pub fn headers(&self) -> &reqwest::header::HeaderMap {
+        self.response.headers()
+    }
+
+    pub async fn text(self) -> Result<String, Ao3ApiError> {
+        Ok(self.response.text().await?)
+    }
+
+    pub async fn bytes(self) -> Result<Vec<u8>, Ao3ApiError> {
+        Ok(self.response.bytes().await?.to_vec())
+    }
+
+    pub fn into_response(self) -> Response {
+        self.response
+    }
+}
+
+/// Create a configured async HTTP client for AO3 operations
+///
+/// # Returns
+/// * Returns a client with the default [`ClientConfig`]
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::networking::client::asynchronous::create_client;
+/// let client = create_client("test").expect("Failed to create client");
+/// ```
+pub fn create_client(useragent: &str) -> Result<Ao3Client, Ao3ApiError> {
+    Ao3Client::new(ClientConfig::new(useragent))
+}
+
+/// Get the requested URL with the provided client
+///
+/// Requests are rate limited and retried according to the client's config.
+/// Redirects are followed up to `max_redirects` hops, a redirect to the login
+/// page fails with [`Ao3ApiError::LoginRequired`].
+///
+/// # Arguments
+/// * `url` - URL to fetch, a path like `/works/123` is resolved against the configured base URL
+/// * `client` - async client to use
+///
+/// # Returns
+/// * Returns a Result with the Page or an error
+///
+/// # Example
+/// ```no_run
+/// # async fn run() {
+/// use ao3_api_rs::networking::client::asynchronous::{create_client, get_page};
+/// let client = create_client("test").unwrap();
+/// let response = get_page("https://archiveofourown.org", &client).await;
+/// # }
+/// ```
+pub async fn get_page(url: &str, client: &Ao3Client) -> Result<Page, Ao3ApiError> {
+    let mut fetch = Fetch::new(url, client.config());
+    loop {
+        client.throttle().await;
+        debug!("Did request to {}", fetch.url);
+        let response = match client.inner().get(&fetch.url).send().await {
+            Ok(response) => response,
+            Err(err) => {
+                sleep(fetch.send_failed(err)?).await;
+                continue;
+            }
+        };
+        match fetch.response(response.status(), response.headers())? {
+            Step::Done => {
+                return Ok(Page {
+                    response,
+                    redirects: fetch.redirects,
+                });
+            }
+            Step::Redirect => {}
+            Step::Retry(wait) => sleep(wait).await,
+        }
+    }
+}
+
+/// Get the initial page and aggregate multiple pages if pagination exists
+///
+/// Prefer [`Pages`] for large listings, this fetches every page before returning.
+///
+/// # Arguments
+/// * `page` - URL of the page to fetch
+/// * `client` - async client to use
+///
+/// # Returns
+/// * Returns parsed HTML with all pages aggregated
+///
+/// # Example
+/// ```no_run
+/// # async fn run() {
+/// use ao3_api_rs::networking::client::asynchronous::{create_client, get_init_page};
+/// let client = create_client("test").unwrap();
+/// let html = get_init_page("https://archiveofourown.org/works".to_string(), &client).await;
+/// # }
+/// ```
+pub async fn get_init_page(page: String, client: &Ao3Client) -> Result<Html, Ao3ApiError> {
+    let mut finalpage = String::new();
+    let mut pages = Pages::new(&page, client);
+    while let Some(document) = pages.next().await {
+        finalpage.push_str(&document?.html());
+    }
+    Ok(Html::parse_document(&finalpage))
+}
+
+/// Get a work page, recognising the pages AO3 shows instead of a work
+///
+/// With `view_adult` set in the client config the adult content warning is
+/// accepted by re-requesting with `view_adult=true`, otherwise it and every
+/// other gate come back as [`Ao3ApiError::Gated`].
+///
+/// # Arguments
+/// * `url` - URL of the work or chapter
+/// * `client` - client to use
+///
+/// # Returns
+/// * Returns the parsed work page
+///
+/// # Example
+/// ```no_run
+/// # async fn run() {
+/// use ao3_api_rs::networking::client::asynchronous::{create_client, get_work_page};
+/// let client = create_client("test").unwrap();
+/// let html = get_work_page("https://archiveofourown.org/works/123456", &client).await;
+/// # }
+/// ```
+pub async fn get_work_page(url: &str, client: &Ao3Client) -> Result<Html, Ao3ApiError> {
+    let url = &client.config().url(url);
+    let text = get_page(url, client)
+        .await
+        .map_err(gate_error)?
+        .text()
+        .await?;
+    let gate = {
+        let document = Html::parse_document(&text);
+        match detect_gate(&document) {
+            None => return Ok(document),
+            Some(gate) => gate,
+        }
+    };
+    if gate != Gate::AdultContent || !client.config().view_adult {
+        return Err(Ao3ApiError::Gated(gate));
+    }
+
+    let text = get_page(&adult_url(url), client)
+        .await
+        .map_err(gate_error)?
+        .text()
+        .await?;
+    let document = Html::parse_document(&text);
+    match detect_gate(&document) {
+        None => Ok(document),
+        Some(gate) => Err(Ao3ApiError::Gated(gate)),
+    }
+}
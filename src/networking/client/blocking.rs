@@ -1,47 +1,172 @@
 //! Blocking HTTP client implementation for AO3
 
-use crate::utils::{arcify, make_selector};
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use regex::Regex;
-use reqwest::blocking::Client;
+use crate::errors::Ao3ApiError;
+use crate::extraction::{Gate, detect_gate};
+use crate::networking::Pages;
+use crate::networking::client::{Fetch, Step, adult_url, gate_error};
+use crate::networking::config::ClientConfig;
+use crate::networking::cookies::CookieJar;
+use crate::networking::ratelimit::RateLimiter;
+use log::debug;
+use reqwest::blocking::{Client, Response};
 use reqwest::{self, redirect};
-use scraper::{ElementRef, Html};
-use std::env::current_dir;
-use std::sync::{Arc, LazyLock, Mutex};
-use std::time::Duration;
-use std::{fs, thread::sleep, time};
+use scraper::Html;
+use std::io::Write;
+use std::sync::Arc;
+use std::thread::sleep;
 
-/// Compiled regex for extracting page numbers (compiled once at first use)
-static PAGE_NUM_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"(?m)(\d*)$").expect("Failed to create page number regex"));
+/// Blocking client for AO3
+///
+/// Wraps a reqwest Client together with its configuration and rate limiter.
+/// Clones share the rate limiter, so one client can be handed to many threads
+/// without exceeding the configured request rate.
+#[derive(Debug, Clone)]
+pub struct Ao3Client {
+    client: Client,
+    limiter: Arc<RateLimiter>,
+    config: Arc<ClientConfig>,
+    cookies: Arc<CookieJar>,
+}
+
+impl Ao3Client {
+    /// Build a client from `config`
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ao3_api_rs::networking::{Ao3Client, ClientConfig, RetryPolicy};
+    /// let config = ClientConfig::new("test")
+    ///     .with_rate_limit(30, 2)
+    ///     .with_retry(RetryPolicy::default().with_max_attempts(3));
+    /// let client = Ao3Client::new(config).expect("Failed to create client");
+    /// ```
+    pub fn new(config: ClientConfig) -> Result<Self, Ao3ApiError> {
+        let cookies = Arc::new(match &config.cookie_file {
+            Some(path) => CookieJar::load_or_default(path)?,
+            None => CookieJar::default(),
+        });
+        let client = Client::builder()
+            .redirect(redirect::Policy::none())
+            .cookie_provider(cookies.clone())
+            .timeout(config.timeout)
+            .user_agent(&config.useragent)
+            .build()?;
+        Ok(Self {
+            client,
+            limiter: Arc::new(RateLimiter::new(config.requests_per_minute, config.burst)),
+            config: Arc::new(config),
+            cookies,
+        })
+    }
+
+    /// Share `limiter` with other clients, e.g. a blocking and an async client
+    pub fn with_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// The underlying reqwest Client, requests sent through it skip the rate limiter
+    pub fn inner(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    pub fn limiter(&self) -> &Arc<RateLimiter> {
+        &self.limiter
+    }
+
+    pub fn cookies(&self) -> &Arc<CookieJar> {
+        &self.cookies
+    }
+
+    /// Save the cookies to the configured `cookie_file`
+    pub fn save_cookies(&self) -> Result<(), Ao3ApiError> {
+        match &self.config.cookie_file {
+            Some(path) => self.cookies.save(path),
+            None => Err(Ao3ApiError::GenericError(
+                "no cookie file configured for this client".to_string(),
+            )),
+        }
+    }
+
+    /// Block until the rate limiter allows another request
+    pub(crate) fn throttle(&self) {
+        let wait = self.limiter.reserve();
+        if !wait.is_zero() {
+            debug!("Rate limited, waiting {:?}", wait);
+            sleep(wait);
+        }
+    }
+}
+
+/// A successful response together with the redirects taken to reach it
+#[derive(Debug)]
+pub struct Page {
+    response: Response,
+    /// URLs that redirected, in the order they were visited
+    pub redirects: Vec<String>,
+}
+
+impl Page {
+    /// Final URL of the page after following redirects
+    pub fn url(&self) -> &reqwest::Url {
+        self.response.url()
+    }
+
+    pub fn status(&self) -> reqwest::StatusCode {
+        self.response.status()
+    }
+
+    pub fn headers(&self) -> &reqwest::header::HeaderMap {
+        self.response.headers()
+    }
+
+    pub fn text(self) -> Result<String, Ao3ApiError> {
+        Ok(self.response.text()?)
+    }
+
+    pub fn bytes(self) -> Result<Vec<u8>, Ao3ApiError> {
+        Ok(self.response.bytes()?.to_vec())
+    }
+
+    /// Stream the body into `writer` without holding it in memory, returns the bytes written
+    pub fn copy_to<W: Write + ?Sized>(mut self, writer: &mut W) -> Result<u64, Ao3ApiError> {
+        Ok(self.response.copy_to(writer)?)
+    }
+
+    pub fn into_response(self) -> Response {
+        self.response
+    }
+}
 
 /// Create a configured HTTP client for AO3 operations
 ///
 /// # Returns
-/// * Returns a configured reqwest Client
+/// * Returns a client with the default [`ClientConfig`]
 ///
 /// # Example
 /// ```no_run
 /// use ao3_api_rs::networking::create_client;
 /// let client = create_client("test").expect("Failed to create client");
 /// ```
-pub fn create_client(useragent: &str) -> Result<Client, reqwest::Error> {
-    Client::builder()
-        .redirect(redirect::Policy::none())
-        .cookie_store(true)
-        .timeout(Duration::new(960, 0))
-        .user_agent(useragent)
-        .build()
+pub fn create_client(useragent: &str) -> Result<Ao3Client, Ao3ApiError> {
+    Ao3Client::new(ClientConfig::new(useragent))
 }
 
 /// Get the requested URL with the provided client
 ///
+/// Requests are rate limited and retried according to the client's config.
+/// Redirects are followed up to `max_redirects` hops, a redirect to the login
+/// page fails with [`Ao3ApiError::LoginRequired`].
+///
 /// # Arguments
-/// * `url` - URL to fetch
-/// * `client` - reqwest Client to use
+/// * `url` - URL to fetch, a path like `/works/123` is resolved against the configured base URL
+/// * `client` - client to use
 ///
 /// # Returns
-/// * Returns a Result with the Response or an error
+/// * Returns a Result with the Page or an error
 ///
 /// # Example
 /// ```no_run
@@ -49,82 +174,38 @@ pub fn create_client(useragent: &str) -> Result<Client, reqwest::Error> {
 /// let client = create_client("test").unwrap();
 /// let response = get_page("https://archiveofourown.org", &client);
 /// ```
-pub fn get_page(url: &str, client: &Client) -> Result<reqwest::blocking::Response, reqwest::Error> {
-    println!("Did request to {}", url);
-    let response = client.get(url).send().expect("Get request failed");
-    println!("{}", response.status());
-
-    match response.status() {
-        // handle redirect
-        status
-            if (status == reqwest::StatusCode::FOUND
-                || status == reqwest::StatusCode::MOVED_PERMANENTLY)
-                && !url.contains("login") =>
-        {
-            // get the redirect location
-            let i = response
-                .headers()
-                .get("location")
-                .expect("Getting location value for redirect failed")
-                .to_str()
-                .expect("Failed to convert location header to string");
-            // TODO: check for infinite redirect loops
-            println!("Following redirect");
-            sleep(time::Duration::from_secs(2));
-            let redirect_url = if i.starts_with("http") {
-                i.to_string()
-            } else {
-                format!("https://archiveofourown.org{}", i)
-            };
-            get_page(&redirect_url, client)
-        }
-        // handle timeout
-        status if matches!(status.as_u16(), 503 | 408 | 429 | 525 | 502 | 524) => {
-            // 503 debug
-            let writeto = format!(
-                "{}/output/",
-                current_dir()
-                    .expect("Failed to get current directory")
-                    .display()
-            );
-            // set default retry time
-            let mut retrytime = 20;
-            // try to set retrytime to requested timeout
-            if let Some(retry_header) = response.headers().get("Retry_After") {
-                retrytime = retry_header
-                    .to_str()
-                    .ok()
-                    .and_then(|s| s.parse().ok())
-                    .unwrap_or(20);
+pub fn get_page(url: &str, client: &Ao3Client) -> Result<Page, Ao3ApiError> {
+    let mut fetch = Fetch::new(url, client.config());
+    loop {
+        client.throttle();
+        debug!("Did request to {}", fetch.url);
+        let response = match client.inner().get(&fetch.url).send() {
+            Ok(response) => response,
+            Err(err) => {
+                sleep(fetch.send_failed(err)?);
+                continue;
             }
-            // write debug file
-            if let Ok(text) = response.text() {
-                let _ = fs::write(format!("{}debug.html", writeto), text);
+        };
+        match fetch.response(response.status(), response.headers())? {
+            Step::Done => {
+                return Ok(Page {
+                    response,
+                    redirects: fetch.redirects,
+                });
             }
-
-            sleep(time::Duration::from_secs(retrytime));
-
-            println!("Service Unavailable, Retrying");
-
-            get_page(url, client)
-        }
-        reqwest::StatusCode::OK => {
-            sleep(time::Duration::from_secs(5));
-            Ok(response)
-        }
-        status => {
-            // I don't want to be blindly doing things when I don't know what we are supposed to do so we just panic.
-            println!("Unknown status: {}", status);
-            Err(response.error_for_status().unwrap_err())
+            Step::Redirect => {}
+            Step::Retry(wait) => sleep(wait),
         }
     }
 }
 
 /// Get the initial page and aggregate multiple pages if pagination exists
 ///
+/// Prefer [`Pages`] for large listings, this fetches every page before returning.
+///
 /// # Arguments
 /// * `page` - URL of the page to fetch
-/// * `client` - reqwest Client to use
+/// * `client` - client to use
 ///
 /// # Returns
 /// * Returns parsed HTML with all pages aggregated
@@ -135,70 +216,53 @@ pub fn get_page(url: &str, client: &Client) -> Result<reqwest::blocking::Respons
 /// let client = create_client("test").unwrap();
 /// let html = get_init_page("https://archiveofourown.org/works".to_string(), &client);
 /// ```
-pub fn get_init_page(page: String, client: &Client) -> Html {
-    let ficpage = get_page(&page, client);
-    let page1 = Html::parse_document(
-        &(ficpage
-            .expect("Failed to get fic page")
-            .text()
-            .expect("failed to get fic page text")),
-    );
-    // Check if their is more then one page
-    let selector = make_selector(r#"ol[class="pagination actions"]"#)
-        .expect("failed to make selector for pages");
-    let mut nav = page1.select(&selector);
-    // We just grab item one
-    let atags = make_selector(r#"a"#).expect("failed to make selector for atags");
-    let mut finalpage = page1.html();
-    // Handle if their is no nav bar
-    if nav.clone().count() != 0 {
-        let page = nav.next().expect("failed to get navbar").select(&atags);
-        let vec: Vec<String> = page
-            .filter_map(|item: ElementRef<'_>| -> Option<String> {
-                let partitle = item
-                    .parent()
-                    .expect("failed to get node parent")
-                    .value()
-                    .as_element()
-                    .expect("failed to convert a tag node to element")
-                    .attr("title");
-                if partitle.unwrap_or("LOL") != "next" {
-                    Some(
-                        item.value()
-                            .attr("href")
-                            .expect("Failed to get atag href")
-                            .to_string(),
-                    )
-                } else {
-                    None
-                }
-            })
-            .collect();
-
-        let pager = vec.last().expect("failed to get final page in vec");
-
-        // Use a regex to extract the page numbers
-        let lastpage = PAGE_NUM_REGEX.captures_iter(pager).next();
-        let finalvec: Arc<Mutex<Vec<String>>> = arcify(Vec::new());
-        let numlist = 1..lastpage.unwrap().get(1).unwrap().as_str().parse().unwrap();
-
-        numlist.into_par_iter().for_each(|i| {
-            finalvec.lock().expect("Failed to lock").push(
-                PAGE_NUM_REGEX
-                    .replace_all(pager, i.to_string())
-                    .into_owned(),
-            )
-        });
+pub fn get_init_page(page: String, client: &Ao3Client) -> Result<Html, Ao3ApiError> {
+    let mut finalpage = String::new();
+    for document in Pages::new(&page, client) {
+        finalpage.push_str(&document?.html());
+    }
+    Ok(Html::parse_document(&finalpage))
+}
 
-        for i in finalvec.lock().expect("Failed to lock").iter() {
-            let url = format!("https://archiveofourown.org/{}", i);
-            finalpage.push_str(
-                &get_page(&url, client)
-                    .expect("Getting page failed")
-                    .text()
-                    .expect("Converting page to text failed"),
-            );
+/// Get a work page, recognising the pages AO3 shows instead of a work
+///
+/// With `view_adult` set in the client config the adult content warning is
+/// accepted by re-requesting with `view_adult=true`, otherwise it and every
+/// other gate come back as [`Ao3ApiError::Gated`].
+///
+/// # Arguments
+/// * `url` - URL of the work or chapter
+/// * `client` - client to use
+///
+/// # Returns
+/// * Returns the parsed work page
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::networking::{create_client, get_work_page};
+/// let client = create_client("test").unwrap();
+/// let html = get_work_page("https://archiveofourown.org/works/123456", &client);
+/// ```
+pub fn get_work_page(url: &str, client: &Ao3Client) -> Result<Html, Ao3ApiError> {
+    let url = &client.config().url(url);
+    let text = get_page(url, client).map_err(gate_error)?.text()?;
+    let gate = {
+        let document = Html::parse_document(&text);
+        match detect_gate(&document) {
+            None => return Ok(document),
+            Some(gate) => gate,
         }
+    };
+    if gate != Gate::AdultContent || !client.config().view_adult {
+        return Err(Ao3ApiError::Gated(gate));
+    }
+
+    let text = get_page(&adult_url(url), client)
+        .map_err(gate_error)?
+        .text()?;
+    let document = Html::parse_document(&text);
+    match detect_gate(&document) {
+        None => Ok(document),
+        Some(gate) => Err(Ao3ApiError::Gated(gate)),
     }
-    Html::parse_document(&finalpage)
 }
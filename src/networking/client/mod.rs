@@ -1,3 +1,161 @@
 //! Client module for HTTP operations
 
+#[cfg(feature = "async")]
+pub mod asynchronous;
 pub mod blocking;
+
+use crate::errors::Ao3ApiError;
+use crate::extraction::Gate;
+use crate::networking::config::ClientConfig;
+use crate::networking::retry::{StatusAction, parse_retry_after};
+use crate::utils::join_url;
+use log::{debug, warn};
+use reqwest::header::{HeaderMap, LOCATION, RETRY_AFTER};
+use reqwest::{StatusCode, Url};
+use std::time::Duration;
+
+/// What `get_page` does after a response
+pub(crate) enum Step {
+    /// The response is the page
+    Done,
+    /// Request the redirect target in [`Fetch::url`] right away
+    Redirect,
+    /// Wait this long, then send the same request again
+    Retry(Duration),
+}
+
+/// Redirect and retry bookkeeping for one `get_page` call
+///
+/// Shared by the blocking and async clients, which only send the requests
+/// and sleep the way they were told to.
+pub(crate) struct Fetch<'a> {
+    config: &'a ClientConfig,
+    /// URL to request next
+    pub(crate) url: String,
+    /// Every URL that redirected so far
+    pub(crate) redirects: Vec<String>,
+    attempt: u32,
+}
+
+impl<'a> Fetch<'a> {
+    pub(crate) fn new(url: &str, config: &'a ClientConfig) -> Self {
+        Self {
+            config,
+            url: config.url(url),
+            redirects: Vec::new(),
+            attempt: 1,
+        }
+    }
+
+    /// Decide what to do after sending the request failed
+    ///
+    /// # Returns
+    /// * Returns how long to wait before sending it again, or the error when
+    ///   it isn't a connection problem or the attempts are used up
+    pub(crate) fn send_failed(&mut self, err: reqwest::Error) -> Result<Duration, Ao3ApiError> {
+        let policy = &self.config.retry;
+        // connection problems are usually transient, anything else isn't
+        if (err.is_connect() || err.is_timeout()) && self.attempt < policy.max_attempts {
+            warn!("Request to {} failed: {}, retrying", self.url, err);
+            let wait = policy.backoff(self.attempt);
+            self.attempt += 1;
+            return Ok(wait);
+        }
+        Err(err.into())
+    }
+
+    /// Decide what to do with a response of `status` carrying `headers`
+    ///
+    /// # Returns
+    /// * Returns the next [`Step`], or the error that ends the request
+    pub(crate) fn response(
+        &mut self,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Result<Step, Ao3ApiError> {
+        debug!("{} returned {}", self.url, status);
+        let policy = &self.config.retry;
+        match policy.action(status) {
+            StatusAction::Success => Ok(Step::Done),
+            StatusAction::Redirect => {
+                let location = headers
+                    .get(LOCATION)
+                    .and_then(|location| location.to_str().ok())
+                    .ok_or_else(|| Ao3ApiError::Parse {
+                        what: "redirect location".to_string(),
+                        url: self.url.clone(),
+                    })?;
+                self.redirects.push(self.url.clone());
+                self.url = next_hop(&self.redirects, location, self.config)?;
+                debug!("Following redirect to {}", self.url);
+                Ok(Step::Redirect)
+            }
+            StatusAction::Retry => {
+                let retry_after = headers
+                    .get(RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(parse_retry_after);
+                let wait = policy.retry_wait(status, &self.url, retry_after, self.attempt)?;
+                warn!("{} returned {}, retrying in {:?}", self.url, status, wait);
+                self.attempt += 1;
+                Ok(Step::Retry(wait))
+            }
+            // I don't want to be blindly doing things when I don't know what we are supposed to do so we bail.
+            StatusAction::Fail => Err(Ao3ApiError::Http {
+                status,
+                url: self.url.clone(),
+            }),
+        }
+    }
+}
+
+/// Work out where a redirect from the last URL in `chain` to `location` goes
+///
+/// `chain` holds every URL that redirected so far, including the current one.
+/// Returns the absolute URL to request next, or an error if it leads to the
+/// login page, back to a URL we already visited, or past `max_redirects` hops.
+/// Relative locations are resolved against the URL that redirected.
+pub(crate) fn next_hop(
+    chain: &[String],
+    location: &str,
+    config: &ClientConfig,
+) -> Result<String, Ao3ApiError> {
+    let next = match chain.last() {
+        Some(current) => join_url(current, location),
+        None => config.url(location),
+    };
+    // restricted works send logged out users to the login page
+    if next.contains("/users/login") {
+        return Err(Ao3ApiError::LoginRequired {
+            url: chain.first().cloned().unwrap_or_default(),
+        });
+    }
+    if chain.contains(&next) || chain.len() > config.max_redirects {
+        let mut chain = chain.to_vec();
+        chain.push(next);
+        return Err(Ao3ApiError::RedirectLoop(chain));
+    }
+    Ok(next)
+}
+
+/// Add `view_adult=true` to `url`, keeping its other parameters
+pub(crate) fn adult_url(url: &str) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    if !parsed.query_pairs().any(|(key, _)| key == "view_adult") {
+        parsed.query_pairs_mut().append_pair("view_adult", "true");
+    }
+    parsed.to_string()
+}
+
+/// Turn errors that mean "the work is there but you can't see it" into gates
+pub(crate) fn gate_error(err: Ao3ApiError) -> Ao3ApiError {
+    match err {
+        Ao3ApiError::LoginRequired { .. } => Ao3ApiError::Gated(Gate::LoginRequired),
+        Ao3ApiError::Http { status, .. } if status == StatusCode::NOT_FOUND => {
+            Ao3ApiError::Gated(Gate::NotFound)
+        }
+        err => err,
+    }
+}
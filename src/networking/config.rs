@@ -0,0 +1,90 @@
+//! Configuration shared by the blocking and async clients
+use crate::networking::retry::RetryPolicy;
+use crate::utils::join_url;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Where requests go unless the config says otherwise
+pub static DEFAULT_BASE_URL: &str = "https://archiveofourown.org";
+
+/// Settings for an [`Ao3Client`](crate::networking::Ao3Client)
+///
+/// The defaults pace requests like the old hardcoded sleeps did, roughly one
+/// request every five seconds.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub useragent: String,
+    /// Scheme and host requests are sent to, without a trailing slash
+    pub base_url: String,
+    /// Timeout for a single request
+    pub timeout: Duration,
+    /// Requests allowed per minute, 0 disables rate limiting
+    pub requests_per_minute: u32,
+    /// Requests that may be sent back to back after a quiet period
+    pub burst: u32,
+    pub retry: RetryPolicy,
+    /// Redirects followed for one request before giving up
+    pub max_redirects: usize,
+    /// Re-request works behind the adult content warning with `view_adult=true`
+    pub view_adult: bool,
+    /// JSON file the client's cookies are restored from and saved to
+    pub cookie_file: Option<PathBuf>,
+}
+
+impl ClientConfig {
+    pub fn new(useragent: &str) -> Self {
+        Self {
+            useragent: useragent.to_string(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            timeout: Duration::new(960, 0),
+            requests_per_minute: 12,
+            burst: 1,
+            retry: RetryPolicy::default(),
+            max_redirects: 10,
+            view_adult: false,
+            cookie_file: None,
+        }
+    }
+
+    /// Send requests to another host, e.g. a mirror or a local stand-in for AO3
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Resolve `href` against the configured host, absolute URLs are kept as they are
+    pub fn url(&self, href: &str) -> String {
+        join_url(&self.base_url, href)
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_rate_limit(mut self, requests_per_minute: u32, burst: u32) -> Self {
+        self.requests_per_minute = requests_per_minute;
+        self.burst = burst;
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    pub fn with_view_adult(mut self, view_adult: bool) -> Self {
+        self.view_adult = view_adult;
+        self
+    }
+
+    pub fn with_cookie_file(mut self, cookie_file: impl Into<PathBuf>) -> Self {
+        self.cookie_file = Some(cookie_file.into());
+        self
+    }
+}
@@ -0,0 +1,157 @@
+//! Cookie jar that can be saved to and restored from a JSON file
+//!
+//! reqwest's own cookie store can't be serialized, so the clients use this
+//! jar instead. Restoring a saved session skips the token and login requests,
+//! which AO3 rate limits much harder than regular page loads.
+use crate::errors::Ao3ApiError;
+use cookie_store::{Cookie, CookieStore, RawCookie};
+use reqwest::Url;
+use reqwest::header::HeaderValue;
+use std::fs::File;
+#[cfg(unix)]
+use std::fs::{OpenOptions, Permissions};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Cookies AO3 only sets after a login
+///
+/// `_otwarchive_session` isn't one of them, every guest gets it too.
+static SESSION_COOKIES: [&str; 2] = ["user_credentials", "remember_user_token"];
+
+/// Thread safe cookie jar shared by a client and all of its clones
+#[derive(Debug, Default)]
+pub struct CookieJar(RwLock<CookieStore>);
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a jar saved with [`CookieJar::save`], dropping cookies that expired since
+    ///
+    /// # Arguments
+    /// * `path` - JSON file written by `save`
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ao3_api_rs::networking::CookieJar;
+    /// let jar = CookieJar::load("cookies.json").expect("Failed to load cookies");
+    /// ```
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Ao3ApiError> {
+        let cookies: Vec<Cookie<'static>> =
+            serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        let store =
+            CookieStore::from_cookies(cookies.into_iter().map(Ok::<_, Ao3ApiError>), false)?;
+        Ok(Self(RwLock::new(store)))
+    }
+
+    /// Load the jar at `path` if it exists, otherwise start empty
+    pub fn load_or_default(path: impl AsRef<Path>) -> Result<Self, Ao3ApiError> {
+        if path.as_ref().exists() {
+            Self::load(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Save every unexpired cookie to `path` as JSON
+    ///
+    /// AO3's session cookie has no expiry date, so unlike most cookie stores this
+    /// also keeps cookies that would normally end with the browser session.
+    /// The file holds a logged in session, so on unix it is only readable by its owner.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Ao3ApiError> {
+        let mut writer = BufWriter::new(create_private(path.as_ref())?);
+        serde_json::to_writer_pretty(
+            &mut writer,
+            &self.read().iter_unexpired().collect::<Vec<_>>(),
+        )?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Replace the contents of this jar with the cookies saved at `path`
+    pub fn reload(&self, path: impl AsRef<Path>) -> Result<(), Ao3ApiError> {
+        let loaded = Self::load(path)?;
+        *self.write() = loaded
+            .0
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        Ok(())
+    }
+
+    /// Check whether the jar holds an unexpired login cookie for `url`
+    ///
+    /// A guest's jar never passes this check. A logged in one can pass it
+    /// after AO3 ended the session, this only looks at the cookies.
+    pub fn has_session(&self, url: &str) -> bool {
+        let Ok(url) = Url::parse(url) else {
+            return false;
+        };
+        self.read()
+            .matches(&url)
+            .iter()
+            .any(|cookie| SESSION_COOKIES.contains(&cookie.name()))
+    }
+
+    /// Remove every cookie
+    pub fn clear(&self) {
+        self.write().clear();
+    }
+
+    /// Helper function to lock the store for reading, a poisoned lock still holds valid cookies
+    fn read(&self) -> RwLockReadGuard<'_, CookieStore> {
+        self.0
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Helper function to lock the store for writing
+    fn write(&self) -> RwLockWriteGuard<'_, CookieStore> {
+        self.0
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let cookies = cookie_headers
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| RawCookie::parse(value.to_string()).ok());
+        self.write().store_response_cookies(cookies, url);
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let header = self
+            .read()
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if header.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&header).ok()
+    }
+}
+
+/// Helper function to create (or truncate) a file only its owner can read and write
+#[cfg(unix)]
+fn create_private(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // the mode only applies to new files, an existing one keeps its permissions otherwise
+    file.set_permissions(Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+fn create_private(path: &Path) -> io::Result<File> {
+    File::create(path)
+}
@@ -3,9 +3,10 @@
 //! A library for networking operations with Archive of Our Own (AO3).
 //!
 //! This library provides utilities for:
-//! - HTTP client configuration with cookie support
+//! - HTTP client configuration with cookie support, rate limiting and retries
 //! - Fetching and parsing HTML pages from AO3
-//! - AO3 authentication (login, token management)
+//! - Lazily paging through listings
+//! - AO3 authentication (login, session checks, token management)
 //! - HTML utilities (selectors, CSS injection)
 //!
 //! ## Usage - Blocking (default)
@@ -17,21 +18,51 @@
 //! let client = create_client("test").expect("Failed to create client");
 //!
 //! // Login to AO3
-//! login(&client, "log.txt");
+//! login(&client, "log.txt").expect("Failed to log in");
 //!
 //! // Fetch a page
 //! let html = get_page("https://archiveofourown.org/works/123456", &client)
 //!     .expect("Failed to fetch page");
 //! ```
+//!
+//! ## Usage - Async (`async` feature)
+//!
+//! ```no_run
+//! # #[cfg(feature = "async")]
+//! # async fn run() {
+//! use ao3_api_rs::networking::auth::asynchronous::login;
+//! use ao3_api_rs::networking::client::asynchronous::{create_client, get_page};
+//!
+//! let client = create_client("test").expect("Failed to create client");
+//! login(&client, "log.txt").await.expect("Failed to log in");
+//! let html = get_page("https://archiveofourown.org/works/123456", &client)
+//!     .await
+//!     .expect("Failed to fetch page");
+//! # }
+//! ```
 
 // Module declarations
 pub mod auth;
 pub mod client;
+pub mod config;
+pub mod cookies;
+pub mod pagination;
+pub mod ratelimit;
+pub mod retry;
 
 // Re-export commonly used items for convenience
-pub use auth::blocking::{get_token, login};
-pub use auth::{LoginInfo, Token, get_login_info};
-pub use client::blocking::{create_client, get_init_page, get_page};
+pub use auth::blocking::{
+    current_session, get_form_token, get_token, is_logged_in, login, logout, resume_session,
+};
+pub use auth::{CredentialSource, LoginInfo, Session, Token, get_login_info};
+pub use client::blocking::{
+    Ao3Client, Page, create_client, get_init_page, get_page, get_work_page,
+};
+pub use config::{ClientConfig, DEFAULT_BASE_URL};
+pub use cookies::CookieJar;
+pub use pagination::{CommentListing, PageCursor, Pages, WorkListing};
+pub use ratelimit::RateLimiter;
+pub use retry::{RetryPolicy, StatusAction};
 
 // Re-export types from dependencies for convenience
 pub use reqwest::Error as NetworkError;
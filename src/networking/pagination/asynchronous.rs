@@ -0,0 +1,177 @@
+//! Async page iterators
+//!
+//! Async iterators are not in std yet, so these expose an async `next`
+//! method instead of implementing `Iterator`.
+use super::PageCursor;
+use crate::errors::Ao3ApiError;
+use crate::extraction::comments::{collapsed_threads, merge_thread};
+use crate::extraction::{Comment, extract_comments_with_base, extract_listing_with_base, tag_slug};
+use crate::networking::client::asynchronous::Ao3Client;
+use crate::networking::client::asynchronous::get_page;
+use ficdata::FicMetadata;
+use scraper::Html;
+
+/// Pages of an AO3 listing, fetching one page per call to `next`
+///
+/// # Example
+/// ```no_run
+/// # async fn run() {
+/// use ao3_api_rs::networking::client::asynchronous::create_client;
+/// use ao3_api_rs::networking::pagination::asynchronous::Pages;
+/// let client = create_client("test").unwrap();
+/// let mut pages = Pages::new("https://archiveofourown.org/tags/Fluff/works", &client);
+/// while let Some(page) = pages.next().await {
+///     let page = page.expect("Failed to get page");
+/// }
+/// # }
+/// ```
+pub struct Pages<'a> {
+    client: &'a Ao3Client,
+    cursor: PageCursor,
+    remaining: Option<u32>,
+    /// Set once a page failed to load, the cursor still points at that page
+    failed: bool,
+}
+
+impl<'a> Pages<'a> {
+    /// Start at the `page` parameter of `url`, or page 1 if it has none
+    ///
+    /// A path like `/tags/Fluff/works` is resolved against the configured base URL.
+    pub fn new(url: &str, client: &'a Ao3Client) -> Self {
+        Self::from_cursor(PageCursor::new(&client.config().url(url)), client)
+    }
+
+    /// Resume from a previously saved cursor
+    pub fn from_cursor(cursor: PageCursor, client: &'a Ao3Client) -> Self {
+        Self {
+            client,
+            cursor,
+            remaining: None,
+            failed: false,
+        }
+    }
+
+    pub fn with_start_page(mut self, page: u32) -> Self {
+        self.cursor.next_page = page.max(1);
+        self
+    }
+
+    /// Stop after fetching `pages` pages
+    pub fn with_max_pages(mut self, pages: u32) -> Self {
+        self.remaining = Some(pages);
+        self
+    }
+
+    /// Where the iterator currently is, save this to resume later
+    pub fn cursor(&self) -> &PageCursor {
+        &self.cursor
+    }
+
+    /// Fetch the next page, `None` once the listing or the page limit is exhausted
+    #[allow(clippy::should_implement_trait)]
+    pub async fn next(&mut self) -> Option<Result<Html, Ao3ApiError>> {
+        if self.failed || self.cursor.finished || self.remaining == Some(0) {
+            return None;
+        }
+        let text = match get_page(&self.cursor.next_url(), self.client).await {
+            Ok(page) => page.text().await,
+            Err(err) => Err(err),
+        };
+        let document = match text {
+            Ok(text) => Html::parse_document(&text),
+            Err(err) => {
+                // stop here, the cursor stays on the failed page so a resumed crawl retries it
+                self.failed = true;
+                return Some(Err(err));
+            }
+        };
+        self.cursor.advance(&document);
+        self.remaining = self.remaining.map(|remaining| remaining - 1);
+        Some(Ok(document))
+    }
+}
+
+/// Work blurbs of a listing, one page of `FicMetadata` per call to `next`
+pub struct WorkListing<'a> {
+    pages: Pages<'a>,
+}
+
+impl<'a> WorkListing<'a> {
+    pub fn new(url: &str, client: &'a Ao3Client) -> Self {
+        Self::from_pages(Pages::new(url, client))
+    }
+
+    /// Works with the tag `name`, see [`tag_slug`] for how it is turned into a URL
+    pub fn for_tag(name: &str, client: &'a Ao3Client) -> Self {
+        Self::new(&format!("/tags/{}/works", tag_slug(name)), client)
+    }
+
+    /// Wrap an already configured [`Pages`]
+    pub fn from_pages(pages: Pages<'a>) -> Self {
+        Self { pages }
+    }
+
+    pub fn cursor(&self) -> &PageCursor {
+        self.pages.cursor()
+    }
+
+    /// Fetch the next page and extract its blurbs, each on its own
+    #[allow(clippy::should_implement_trait)]
+    pub async fn next(
+        &mut self,
+    ) -> Option<Result<Vec<Result<FicMetadata, Ao3ApiError>>, Ao3ApiError>> {
+        let page = self.pages.next().await?;
+        Some(page.map(|document| {
+            extract_listing_with_base(&document, &self.pages.client.config().base_url)
+        }))
+    }
+}
+
+/// Comments of a work or chapter, one page of threads per call to `next`
+///
+/// Threads AO3 cut short are fetched from their own pages.
+pub struct CommentListing<'a> {
+    pages: Pages<'a>,
+}
+
+impl<'a> CommentListing<'a> {
+    /// Start at `url`, e.g. `/works/123/comments` or `/chapters/456?show_comments=true`
+    pub fn new(url: &str, client: &'a Ao3Client) -> Self {
+        Self::from_pages(Pages::new(url, client))
+    }
+
+    /// Wrap an already configured [`Pages`]
+    pub fn from_pages(pages: Pages<'a>) -> Self {
+        Self { pages }
+    }
+
+    pub fn cursor(&self) -> &PageCursor {
+        self.pages.cursor()
+    }
+
+    /// Fetch the next page and extract its comment threads
+    #[allow(clippy::should_implement_trait)]
+    pub async fn next(&mut self) -> Option<Result<Vec<Comment>, Ao3ApiError>> {
+        let page = self.pages.next().await?;
+        let base_url = &self.pages.client.config().base_url;
+        let mut comments =
+            match page.and_then(|document| extract_comments_with_base(&document, base_url)) {
+                Ok(comments) => comments,
+                Err(err) => return Some(Err(err)),
+            };
+        while let Some((path, url)) = collapsed_threads(&comments).into_iter().next() {
+            let text = match get_page(&url, self.pages.client).await {
+                Ok(page) => page.text().await,
+                Err(err) => Err(err),
+            };
+            let thread = match text
+                .and_then(|text| extract_comments_with_base(&Html::parse_document(&text), base_url))
+            {
+                Ok(thread) => thread,
+                Err(err) => return Some(Err(err)),
+            };
+            merge_thread(&mut comments, &path, &thread);
+        }
+        Some(Ok(comments))
+    }
+}
@@ -0,0 +1,195 @@
+//! Blocking page iterators
+use super::PageCursor;
+use crate::errors::Ao3ApiError;
+use crate::extraction::comments::{collapsed_threads, merge_thread};
+use crate::extraction::{Comment, extract_comments_with_base, extract_listing_with_base, tag_slug};
+use crate::networking::Ao3Client;
+use crate::networking::get_page;
+use ficdata::FicMetadata;
+use scraper::Html;
+
+/// Iterator over the pages of an AO3 listing, fetching one page per call to `next`
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::networking::{create_client, Pages};
+/// let client = create_client("test").unwrap();
+/// let pages = Pages::new("https://archiveofourown.org/tags/Fluff/works", &client)
+///     .with_start_page(3)
+///     .with_max_pages(2);
+/// for page in pages {
+///     let page = page.expect("Failed to get page");
+/// }
+/// ```
+pub struct Pages<'a> {
+    client: &'a Ao3Client,
+    cursor: PageCursor,
+    remaining: Option<u32>,
+    /// Set once a page failed to load, the cursor still points at that page
+    failed: bool,
+}
+
+impl<'a> Pages<'a> {
+    /// Start at the `page` parameter of `url`, or page 1 if it has none
+    ///
+    /// A path like `/tags/Fluff/works` is resolved against the configured base URL.
+    pub fn new(url: &str, client: &'a Ao3Client) -> Self {
+        Self::from_cursor(PageCursor::new(&client.config().url(url)), client)
+    }
+
+    /// Resume from a previously saved cursor
+    pub fn from_cursor(cursor: PageCursor, client: &'a Ao3Client) -> Self {
+        Self {
+            client,
+            cursor,
+            remaining: None,
+            failed: false,
+        }
+    }
+
+    pub fn with_start_page(mut self, page: u32) -> Self {
+        self.cursor.next_page = page.max(1);
+        self
+    }
+
+    /// Stop after fetching `pages` pages
+    pub fn with_max_pages(mut self, pages: u32) -> Self {
+        self.remaining = Some(pages);
+        self
+    }
+
+    /// Where the iterator currently is, save this to resume later
+    pub fn cursor(&self) -> &PageCursor {
+        &self.cursor
+    }
+}
+
+impl Iterator for Pages<'_> {
+    type Item = Result<Html, Ao3ApiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.cursor.finished || self.remaining == Some(0) {
+            return None;
+        }
+        let document =
+            match get_page(&self.cursor.next_url(), self.client).and_then(|page| page.text()) {
+                Ok(text) => Html::parse_document(&text),
+                Err(err) => {
+                    // stop here, the cursor stays on the failed page so a resumed crawl retries it
+                    self.failed = true;
+                    return Some(Err(err));
+                }
+            };
+        self.cursor.advance(&document);
+        self.remaining = self.remaining.map(|remaining| remaining - 1);
+        Some(Ok(document))
+    }
+}
+
+/// Iterator over the work blurbs of a listing, one page of `FicMetadata` at a time
+///
+/// Blurbs are extracted one by one, so a blurb that fails to parse doesn't
+/// take the rest of its page with it.
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::networking::{create_client, WorkListing};
+/// let client = create_client("test").unwrap();
+/// for fics in WorkListing::new("https://archiveofourown.org/tags/Fluff/works", &client) {
+///     let fics = fics.expect("Failed to get page");
+///     println!("{} works on this page", fics.iter().filter(|fic| fic.is_ok()).count());
+/// }
+/// ```
+pub struct WorkListing<'a> {
+    pages: Pages<'a>,
+}
+
+impl<'a> WorkListing<'a> {
+    pub fn new(url: &str, client: &'a Ao3Client) -> Self {
+        Self::from_pages(Pages::new(url, client))
+    }
+
+    /// Works with the tag `name`, e.g. "Hurt/Comfort" lists `/tags/Hurt*s*Comfort/works`
+    pub fn for_tag(name: &str, client: &'a Ao3Client) -> Self {
+        Self::new(&format!("/tags/{}/works", tag_slug(name)), client)
+    }
+
+    /// Wrap an already configured [`Pages`] iterator
+    pub fn from_pages(pages: Pages<'a>) -> Self {
+        Self { pages }
+    }
+
+    pub fn cursor(&self) -> &PageCursor {
+        self.pages.cursor()
+    }
+}
+
+impl Iterator for WorkListing<'_> {
+    type Item = Result<Vec<Result<FicMetadata, Ao3ApiError>>, Ao3ApiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let page = self.pages.next()?;
+        Some(page.map(|document| {
+            extract_listing_with_base(&document, &self.pages.client.config().base_url)
+        }))
+    }
+}
+
+/// Iterator over the comments of a work or chapter, one page of threads at a time
+///
+/// Threads AO3 cut short are fetched from their own pages, so every page
+/// comes back with all of its replies.
+///
+/// # Example
+/// ```no_run
+/// use ao3_api_rs::networking::{create_client, CommentListing};
+/// let client = create_client("test").unwrap();
+/// for threads in CommentListing::new("/works/123456/comments", &client) {
+///     let threads = threads.expect("Failed to get comments");
+///     let total: usize = threads.iter().map(|comment| comment.thread_len()).sum();
+///     println!("{} comments on this page", total);
+/// }
+/// ```
+pub struct CommentListing<'a> {
+    pages: Pages<'a>,
+}
+
+impl<'a> CommentListing<'a> {
+    /// Start at `url`, e.g. `/works/123/comments` or `/chapters/456?show_comments=true`
+    pub fn new(url: &str, client: &'a Ao3Client) -> Self {
+        Self::from_pages(Pages::new(url, client))
+    }
+
+    /// Wrap an already configured [`Pages`] iterator
+    pub fn from_pages(pages: Pages<'a>) -> Self {
+        Self { pages }
+    }
+
+    pub fn cursor(&self) -> &PageCursor {
+        self.pages.cursor()
+    }
+
+    /// Helper function to fetch the collapsed threads in `comments`, one at a time
+    fn expand(&self, mut comments: Vec<Comment>) -> Result<Vec<Comment>, Ao3ApiError> {
+        let base_url = &self.pages.client.config().base_url;
+        while let Some((path, url)) = collapsed_threads(&comments).into_iter().next() {
+            let text = get_page(&url, self.pages.client)?.text()?;
+            let thread = extract_comments_with_base(&Html::parse_document(&text), base_url)?;
+            merge_thread(&mut comments, &path, &thread);
+        }
+        Ok(comments)
+    }
+}
+
+impl Iterator for CommentListing<'_> {
+    type Item = Result<Vec<Comment>, Ao3ApiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let page = self.pages.next()?;
+        Some(page.and_then(|document| {
+            let comments =
+                extract_comments_with_base(&document, &self.pages.client.config().base_url)?;
+            self.expand(comments)
+        }))
+    }
+}
@@ -0,0 +1,98 @@
+//! Lazy pagination over AO3 listings
+//!
+//! Instead of fetching every page up front like `get_init_page`, the
+//! [`blocking::Pages`] iterator (and its async twin) fetches one page at a
+//! time. Progress is tracked in a serialisable [`PageCursor`] so a crawl can
+//! be stopped and resumed later.
+
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod blocking;
+pub use blocking::{CommentListing, Pages, WorkListing};
+
+use crate::extraction::extract_listing_info;
+use crate::utils::{make_selector, safe_static_selector};
+use crate::{define_selector, make_static};
+use reqwest::Url;
+use scraper::{Html, selector::Selector};
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+define_selector!(
+    NEXT_PAGE_SELECTOR,
+    NEXT_PAGE_SELECTOR_TEXT,
+    r#"ol.pagination li.next a"#
+);
+/// Position in a paginated listing
+///
+/// Save it (it is `Serialize`) to resume a crawl with `Pages::from_cursor`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageCursor {
+    /// Listing URL, any `page` parameter in it is replaced when fetching
+    pub url: String,
+    /// Next page that will be fetched, starting at 1
+    pub next_page: u32,
+    /// Last page of the listing, known once a page has been fetched
+    pub last_page: Option<u32>,
+    /// Set once the last page has been fetched
+    pub finished: bool,
+}
+
+impl PageCursor {
+    /// Create a cursor for `url`, starting at the `page` parameter in it or page 1
+    pub fn new(url: &str) -> Self {
+        let page = Url::parse(url)
+            .ok()
+            .and_then(|url| {
+                url.query_pairs()
+                    .find(|(key, _)| key == "page")
+                    .and_then(|(_, value)| value.parse().ok())
+            })
+            .unwrap_or(1);
+        Self {
+            url: url.to_string(),
+            next_page: page,
+            last_page: None,
+            finished: false,
+        }
+    }
+
+    /// URL of the next page to fetch
+    pub fn next_url(&self) -> String {
+        page_url(&self.url, self.next_page)
+    }
+
+    /// Record that the next page was fetched and parsed as `document`
+    pub(crate) fn advance(&mut self, document: &Html) {
+        self.last_page = extract_listing_info(document)
+            .map(|info| info.last_page)
+            .ok()
+            .or(self.last_page);
+        self.finished = !has_next_page(document);
+        self.next_page += 1;
+    }
+}
+
+/// Replace (or add) the `page` parameter of `url`
+fn page_url(url: &str, page: u32) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    let pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(key, _)| key != "page")
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    parsed
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair("page", &page.to_string());
+    parsed.to_string()
+}
+
+/// Returns true if the pagination bar links to a next page
+fn has_next_page(document: &Html) -> bool {
+    safe_static_selector(NEXT_PAGE_SELECTOR.clone(), NEXT_PAGE_SELECTOR_TEXT)
+        .is_ok_and(|selector| document.select(&selector).next().is_some())
+}
@@ -0,0 +1,57 @@
+//! Token bucket rate limiting for requests to AO3
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Token bucket shared by every clone of a client
+///
+/// Each request takes one token. Tokens refill at `requests_per_minute` and
+/// at most `burst` of them can be saved up. Callers that find the bucket
+/// empty reserve a future token and are told how long to wait for it, so
+/// concurrent threads queue up instead of all firing at once.
+#[derive(Debug)]
+pub struct RateLimiter {
+    bucket: Mutex<Bucket>,
+    capacity: f64,
+    refill_per_sec: f64,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    /// Create a limiter, `requests_per_minute` of 0 disables limiting
+    pub fn new(requests_per_minute: u32, burst: u32) -> Self {
+        let capacity = f64::from(burst.max(1));
+        Self {
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                last: Instant::now(),
+            }),
+            capacity,
+            refill_per_sec: f64::from(requests_per_minute) / 60.0,
+        }
+    }
+
+    /// Take a token and return how long to wait before using it
+    pub fn reserve(&self) -> Duration {
+        if self.refill_per_sec <= 0.0 {
+            return Duration::ZERO;
+        }
+        // a panic while holding the lock can't leave the bucket in a bad state
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        let refilled = now.duration_since(bucket.last).as_secs_f64() * self.refill_per_sec;
+        bucket.tokens = (bucket.tokens + refilled).min(self.capacity);
+        bucket.last = now;
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.refill_per_sec)
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Retry policy and status handling for requests to AO3
+use crate::errors::Ao3ApiError;
+use reqwest::StatusCode;
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, SystemTime};
+
+/// What to do with a response of a given status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusAction {
+    /// Hand the response to the caller
+    Success,
+    /// Follow the `Location` header
+    Redirect,
+    /// Wait and send the request again
+    Retry,
+    /// Give up with an `Http` error
+    Fail,
+}
+
+/// How often and how patiently failed requests are retried
+///
+/// Statuses missing from the decision table fail.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every further retry
+    pub base_delay: Duration,
+    /// Upper bound for the backoff delay
+    pub max_delay: Duration,
+    /// Randomise delays between half and all of the backoff
+    pub jitter: bool,
+    /// Longest `Retry-After` obeyed, a longer one fails with `RateLimited` right away
+    pub max_retry_after: Duration,
+    statuses: HashMap<u16, StatusAction>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        let mut statuses = HashMap::new();
+        statuses.insert(200, StatusAction::Success);
+        for status in [301, 302, 303, 307, 308] {
+            statuses.insert(status, StatusAction::Redirect);
+        }
+        for status in [408, 429, 502, 503, 504, 524, 525] {
+            statuses.insert(status, StatusAction::Retry);
+        }
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(10),
+            max_delay: Duration::from_secs(300),
+            jitter: true,
+            max_retry_after: Duration::from_secs(300),
+            statuses,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    pub fn with_max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_max_retry_after(mut self, max_retry_after: Duration) -> Self {
+        self.max_retry_after = max_retry_after;
+        self
+    }
+
+    /// Override the decision for one status code
+    pub fn with_status_action(mut self, status: u16, action: StatusAction) -> Self {
+        self.statuses.insert(status, action);
+        self
+    }
+
+    /// Look up what to do with `status`
+    pub fn action(&self, status: StatusCode) -> StatusAction {
+        self.statuses
+            .get(&status.as_u16())
+            .copied()
+            .unwrap_or(StatusAction::Fail)
+    }
+
+    /// Delay before retry number `retry` (1 for the first retry)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        if !self.jitter {
+            return delay;
+        }
+        // RandomState is seeded randomly per instance, good enough for jitter
+        let random = RandomState::new().build_hasher().finish();
+        let fraction = 0.5 + (random as f64 / u64::MAX as f64) / 2.0;
+        delay.mul_f64(fraction)
+    }
+
+    /// How long to wait before retrying a `status` response to attempt number `attempt`
+    ///
+    /// # Arguments
+    /// * `status` - status of the response
+    /// * `url` - URL the response came from, for the error
+    /// * `retry_after` - the parsed `Retry-After` header, if any
+    /// * `attempt` - attempts made so far, starting at 1
+    ///
+    /// # Returns
+    /// * Returns the `Retry-After` delay, or the backoff when there is none. Fails
+    ///   once the attempts are used up, or with `RateLimited` when the server
+    ///   asks for a longer wait than `max_retry_after`
+    pub(crate) fn retry_wait(
+        &self,
+        status: StatusCode,
+        url: &str,
+        retry_after: Option<Duration>,
+        attempt: u32,
+    ) -> Result<Duration, Ao3ApiError> {
+        if attempt >= self.max_attempts {
+            return Err(give_up(status, url.to_string(), retry_after));
+        }
+        match retry_after {
+            Some(wait) if wait > self.max_retry_after => {
+                Err(Ao3ApiError::RateLimited { retry_after })
+            }
+            Some(wait) => Ok(wait),
+            None => Ok(self.backoff(attempt)),
+        }
+    }
+}
+
+/// Parse a `Retry-After` header, either delay-seconds or an HTTP-date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    // a date in the past means we may retry right away
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// Error to return once every retry for `status` has been used up
+pub(crate) fn give_up(
+    status: StatusCode,
+    url: String,
+    retry_after: Option<Duration>,
+) -> Ao3ApiError {
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        Ao3ApiError::RateLimited { retry_after }
+    } else {
+        Ao3ApiError::Http { status, url }
+    }
+}
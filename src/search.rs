@@ -0,0 +1,536 @@
+//! Typed builder for the AO3 works search (`/works/search`)
+//!
+//! ```no_run
+//! use ao3_api_rs::networking::create_client;
+//! use ao3_api_rs::search::{NumberRange, Rating, SortColumn, WorkSearch};
+//!
+//! let client = create_client("test").unwrap();
+//! let search = WorkSearch::new()
+//!     .with_fandom("Original Work")
+//!     .with_rating(Rating::GeneralAudiences)
+//!     .with_complete(true)
+//!     .with_word_count(NumberRange::GreaterThan(10000))
+//!     .with_sort_column(SortColumn::KudosCount);
+//! for fics in search.listing(&client).unwrap().take(2) {
+//!     let fics = fics.expect("Failed to get results");
+//! }
+//! let everything = search.execute(&client).expect("Failed to search");
+//! ```
+use crate::errors::Ao3ApiError;
+use crate::networking::config::DEFAULT_BASE_URL;
+use crate::networking::{Ao3Client, WorkListing};
+use crate::utils::join_url;
+use ficdata::FicMetadata;
+use reqwest::Url;
+use std::fmt;
+
+static SEARCH_PATH: &str = "/works/search";
+
+/// Content rating, AO3 only allows filtering on one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rating {
+    NotRated,
+    GeneralAudiences,
+    TeenAndUp,
+    Mature,
+    Explicit,
+}
+
+impl Rating {
+    fn id(self) -> u32 {
+        match self {
+            Rating::NotRated => 9,
+            Rating::GeneralAudiences => 10,
+            Rating::TeenAndUp => 11,
+            Rating::Mature => 12,
+            Rating::Explicit => 13,
+        }
+    }
+}
+
+/// Archive warning
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Warning {
+    CreatorChoseNotToUse,
+    NoArchiveWarningsApply,
+    GraphicViolence,
+    MajorCharacterDeath,
+    RapeNonCon,
+    Underage,
+}
+
+impl Warning {
+    fn id(self) -> u32 {
+        match self {
+            Warning::CreatorChoseNotToUse => 14,
+            Warning::NoArchiveWarningsApply => 16,
+            Warning::GraphicViolence => 17,
+            Warning::MajorCharacterDeath => 18,
+            Warning::RapeNonCon => 19,
+            Warning::Underage => 20,
+        }
+    }
+}
+
+/// Relationship category
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Gen,
+    FemaleMale,
+    MaleMale,
+    Other,
+    FemaleFemale,
+    Multi,
+}
+
+impl Category {
+    fn id(self) -> u32 {
+        match self {
+            Category::Gen => 21,
+            Category::FemaleMale => 22,
+            Category::MaleMale => 23,
+            Category::Other => 24,
+            Category::FemaleFemale => 116,
+            Category::Multi => 2246,
+        }
+    }
+}
+
+/// Column to sort results on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortColumn {
+    BestMatch,
+    Author,
+    Title,
+    DatePosted,
+    DateUpdated,
+    WordCount,
+    Hits,
+    KudosCount,
+    CommentsCount,
+    BookmarksCount,
+}
+
+impl SortColumn {
+    fn as_str(self) -> &'static str {
+        match self {
+            SortColumn::BestMatch => "_score",
+            SortColumn::Author => "authors_to_sort_on",
+            SortColumn::Title => "title_to_sort_on",
+            SortColumn::DatePosted => "created_at",
+            SortColumn::DateUpdated => "revised_at",
+            SortColumn::WordCount => "word_count",
+            SortColumn::Hits => "hits",
+            SortColumn::KudosCount => "kudos_count",
+            SortColumn::CommentsCount => "comments_count",
+            SortColumn::BookmarksCount => "bookmarks_count",
+        }
+    }
+}
+
+/// Direction to sort results in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+impl SortDirection {
+    fn as_str(self) -> &'static str {
+        match self {
+            SortDirection::Ascending => "asc",
+            SortDirection::Descending => "desc",
+        }
+    }
+}
+
+/// Numeric range for word count, kudos, hits, bookmarks and comments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberRange {
+    Exact(u32),
+    LessThan(u32),
+    GreaterThan(u32),
+    /// Inclusive on both ends
+    Between(u32, u32),
+}
+
+impl fmt::Display for NumberRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumberRange::Exact(n) => write!(f, "{}", n),
+            NumberRange::LessThan(n) => write!(f, "<{}", n),
+            NumberRange::GreaterThan(n) => write!(f, ">{}", n),
+            NumberRange::Between(low, high) => write!(f, "{}-{}", low, high),
+        }
+    }
+}
+
+/// Unit for relative dates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnit {
+    Days,
+    Weeks,
+    Months,
+    Years,
+}
+
+impl TimeUnit {
+    fn as_str(self) -> &'static str {
+        match self {
+            TimeUnit::Days => "days",
+            TimeUnit::Weeks => "weeks",
+            TimeUnit::Months => "months",
+            TimeUnit::Years => "years",
+        }
+    }
+}
+
+/// Date range for the last revision of a work
+///
+/// Absolute dates are written as `YYYY-MM-DD`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DateRange {
+    Before(String),
+    After(String),
+    Between(String, String),
+    /// Revised within the last `n` units, e.g. `WithinLast(2, TimeUnit::Weeks)`
+    WithinLast(u32, TimeUnit),
+    /// Revised more than `n` units ago
+    OlderThan(u32, TimeUnit),
+}
+
+impl fmt::Display for DateRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateRange::Before(date) => write!(f, "< {}", date),
+            DateRange::After(date) => write!(f, "> {}", date),
+            DateRange::Between(from, to) => write!(f, "{} - {}", from, to),
+            DateRange::WithinLast(n, unit) => write!(f, "< {} {}", n, unit.as_str()),
+            DateRange::OlderThan(n, unit) => write!(f, "> {} {}", n, unit.as_str()),
+        }
+    }
+}
+
+/// Name of a search form field, e.g. `work_search[query]`
+fn field(name: &str) -> String {
+    format!("work_search[{}]", name)
+}
+
+/// Builder for an AO3 works search
+///
+/// Every field is optional, unset fields are left out of the URL.
+#[derive(Debug, Clone, Default)]
+pub struct WorkSearch {
+    query: Option<String>,
+    title: Option<String>,
+    creators: Option<String>,
+    fandoms: Vec<String>,
+    characters: Vec<String>,
+    relationships: Vec<String>,
+    freeforms: Vec<String>,
+    rating: Option<Rating>,
+    warnings: Vec<Warning>,
+    categories: Vec<Category>,
+    complete: Option<bool>,
+    crossover: Option<bool>,
+    single_chapter: bool,
+    word_count: Option<NumberRange>,
+    kudos: Option<NumberRange>,
+    hits: Option<NumberRange>,
+    bookmarks: Option<NumberRange>,
+    comments: Option<NumberRange>,
+    revised_at: Option<DateRange>,
+    language: Option<String>,
+    sort_column: Option<SortColumn>,
+    sort_direction: Option<SortDirection>,
+}
+
+impl WorkSearch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Free text query, supports AO3's search syntax
+    pub fn with_query(mut self, query: impl Into<String>) -> Self {
+        self.query = Some(query.into());
+        self
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn with_creators(mut self, creators: impl Into<String>) -> Self {
+        self.creators = Some(creators.into());
+        self
+    }
+
+    /// Adds a fandom tag name, can be called multiple times
+    pub fn with_fandom(mut self, fandom: impl Into<String>) -> Self {
+        self.fandoms.push(fandom.into());
+        self
+    }
+
+    /// Adds a character tag name, can be called multiple times
+    pub fn with_character(mut self, character: impl Into<String>) -> Self {
+        self.characters.push(character.into());
+        self
+    }
+
+    /// Adds a relationship tag name, can be called multiple times
+    pub fn with_relationship(mut self, relationship: impl Into<String>) -> Self {
+        self.relationships.push(relationship.into());
+        self
+    }
+
+    /// Adds a freeform (additional) tag name, can be called multiple times
+    pub fn with_freeform(mut self, freeform: impl Into<String>) -> Self {
+        self.freeforms.push(freeform.into());
+        self
+    }
+
+    pub fn with_rating(mut self, rating: Rating) -> Self {
+        self.rating = Some(rating);
+        self
+    }
+
+    /// Adds an archive warning, can be called multiple times
+    pub fn with_warning(mut self, warning: Warning) -> Self {
+        self.warnings.push(warning);
+        self
+    }
+
+    /// Adds a relationship category, can be called multiple times
+    pub fn with_category(mut self, category: Category) -> Self {
+        self.categories.push(category);
+        self
+    }
+
+    /// `true` for complete works only, `false` for works in progress only
+    pub fn with_complete(mut self, complete: bool) -> Self {
+        self.complete = Some(complete);
+        self
+    }
+
+    /// `true` for crossovers only, `false` to exclude crossovers
+    pub fn with_crossover(mut self, crossover: bool) -> Self {
+        self.crossover = Some(crossover);
+        self
+    }
+
+    pub fn with_single_chapter(mut self, single_chapter: bool) -> Self {
+        self.single_chapter = single_chapter;
+        self
+    }
+
+    pub fn with_word_count(mut self, range: NumberRange) -> Self {
+        self.word_count = Some(range);
+        self
+    }
+
+    pub fn with_kudos(mut self, range: NumberRange) -> Self {
+        self.kudos = Some(range);
+        self
+    }
+
+    pub fn with_hits(mut self, range: NumberRange) -> Self {
+        self.hits = Some(range);
+        self
+    }
+
+    pub fn with_bookmarks(mut self, range: NumberRange) -> Self {
+        self.bookmarks = Some(range);
+        self
+    }
+
+    pub fn with_comments(mut self, range: NumberRange) -> Self {
+        self.comments = Some(range);
+        self
+    }
+
+    pub fn with_revised_at(mut self, range: DateRange) -> Self {
+        self.revised_at = Some(range);
+        self
+    }
+
+    /// Language code as used by AO3, e.g. `en`
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    pub fn with_sort_column(mut self, column: SortColumn) -> Self {
+        self.sort_column = Some(column);
+        self
+    }
+
+    pub fn with_sort_direction(mut self, direction: SortDirection) -> Self {
+        self.sort_direction = Some(direction);
+        self
+    }
+
+    /// Query parameters for this search in the order AO3's own form sends them
+    fn params(&self) -> Vec<(String, String)> {
+        let mut params: Vec<(String, String)> = vec![("commit".into(), "Search".into())];
+
+        if let Some(query) = &self.query {
+            params.push((field("query"), query.clone()));
+        }
+        if let Some(title) = &self.title {
+            params.push((field("title"), title.clone()));
+        }
+        if let Some(creators) = &self.creators {
+            params.push((field("creators"), creators.clone()));
+        }
+        if let Some(revised_at) = &self.revised_at {
+            params.push((field("revised_at"), revised_at.to_string()));
+        }
+        if let Some(complete) = self.complete {
+            params.push((
+                field("complete"),
+                if complete { "T" } else { "F" }.to_string(),
+            ));
+        }
+        if let Some(crossover) = self.crossover {
+            params.push((
+                field("crossover"),
+                if crossover { "T" } else { "F" }.to_string(),
+            ));
+        }
+        if self.single_chapter {
+            params.push((field("single_chapter"), "1".to_string()));
+        }
+        if let Some(word_count) = self.word_count {
+            params.push((field("word_count"), word_count.to_string()));
+        }
+        if let Some(language) = &self.language {
+            params.push((field("language_id"), language.clone()));
+        }
+        if !self.fandoms.is_empty() {
+            params.push((field("fandom_names"), self.fandoms.join(",")));
+        }
+        if let Some(rating) = self.rating {
+            params.push((field("rating_ids"), rating.id().to_string()));
+        }
+        for warning in &self.warnings {
+            params.push((
+                field("archive_warning_ids") + "[]",
+                warning.id().to_string(),
+            ));
+        }
+        for category in &self.categories {
+            params.push((field("category_ids") + "[]", category.id().to_string()));
+        }
+        if !self.characters.is_empty() {
+            params.push((field("character_names"), self.characters.join(",")));
+        }
+        if !self.relationships.is_empty() {
+            params.push((field("relationship_names"), self.relationships.join(",")));
+        }
+        if !self.freeforms.is_empty() {
+            params.push((field("freeform_names"), self.freeforms.join(",")));
+        }
+        if let Some(hits) = self.hits {
+            params.push((field("hits"), hits.to_string()));
+        }
+        if let Some(kudos) = self.kudos {
+            params.push((field("kudos_count"), kudos.to_string()));
+        }
+        if let Some(comments) = self.comments {
+            params.push((field("comments_count"), comments.to_string()));
+        }
+        if let Some(bookmarks) = self.bookmarks {
+            params.push((field("bookmarks_count"), bookmarks.to_string()));
+        }
+        if let Some(column) = self.sort_column {
+            params.push((field("sort_column"), column.as_str().to_string()));
+        }
+        if let Some(direction) = self.sort_direction {
+            params.push((field("sort_direction"), direction.as_str().to_string()));
+        }
+
+        params
+    }
+
+    /// Serialise the search into a percent-encoded AO3 search URL
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ao3_api_rs::search::WorkSearch;
+    /// let url = WorkSearch::new().with_title("Tea & Biscuits").url().unwrap();
+    /// ```
+    pub fn url(&self) -> Result<Url, Ao3ApiError> {
+        self.url_with_base(DEFAULT_BASE_URL)
+    }
+
+    /// URL of the search results on a mirror or proxy at `base_url`
+    ///
+    /// # Returns
+    /// * Returns an error when `base_url` isn't an absolute URL, e.g. when it has no scheme
+    pub fn url_with_base(&self, base_url: &str) -> Result<Url, Ao3ApiError> {
+        Url::parse_with_params(&join_url(base_url, SEARCH_PATH), self.params()).map_err(|_| {
+            Ao3ApiError::Parse {
+                what: "search url".to_string(),
+                url: base_url.to_string(),
+            }
+        })
+    }
+
+    /// Run the search with the blocking client, fetching result pages lazily
+    ///
+    /// # Arguments
+    /// * `client` - reqwest Client to use
+    ///
+    /// # Returns
+    /// * Returns a [`WorkListing`] over the result pages, or an error if the
+    ///   client's base URL can't be turned into a search URL
+    pub fn listing<'a>(&self, client: &'a Ao3Client) -> Result<WorkListing<'a>, Ao3ApiError> {
+        let url = self.url_with_base(&client.config().base_url)?;
+        Ok(WorkListing::new(url.as_str(), client))
+    }
+
+    /// Run the search with the blocking client and extract every result blurb
+    ///
+    /// # Arguments
+    /// * `client` - reqwest Client to use
+    ///
+    /// # Returns
+    /// * Returns the metadata of every work on every result page, or the first
+    ///   page or blurb that failed, use [`WorkSearch::listing`] to skip bad blurbs
+    pub fn execute(&self, client: &Ao3Client) -> Result<Vec<FicMetadata>, Ao3ApiError> {
+        let mut fics = Vec::new();
+        for page in self.listing(client)? {
+            for fic in page? {
+                fics.push(fic?);
+            }
+        }
+        Ok(fics)
+    }
+
+    /// Run the search with the async client, fetching result pages lazily
+    #[cfg(feature = "async")]
+    pub fn listing_async<'a>(
+        &self,
+        client: &'a crate::networking::client::asynchronous::Ao3Client,
+    ) -> Result<crate::networking::pagination::asynchronous::WorkListing<'a>, Ao3ApiError> {
+        let url = self.url_with_base(&client.config().base_url)?;
+        Ok(crate::networking::pagination::asynchronous::WorkListing::new(url.as_str(), client))
+    }
+
+    /// Run the search with the async client and extract every result blurb
+    #[cfg(feature = "async")]
+    pub async fn execute_async(
+        &self,
+        client: &crate::networking::client::asynchronous::Ao3Client,
+    ) -> Result<Vec<FicMetadata>, Ao3ApiError> {
+        let mut listing = self.listing_async(client)?;
+        let mut fics = Vec::new();
+        while let Some(page) = listing.next().await {
+            for fic in page? {
+                fics.push(fic?);
+            }
+        }
+        Ok(fics)
+    }
+}
@@ -0,0 +1,319 @@
+//! In-process stand-in for AO3, for tests
+//!
+//! Enabled with the `testing` feature. [`MockServer`] listens on a local port
+//! and answers requests from a table of canned [`Reply`]s, so `get_page`,
+//! `get_init_page` and `login` can run without touching archiveofourown.org.
+//! Page fixtures live in `tests/fixtures` and are loaded with [`fixture`].
+//!
+//! # Example
+//! ```
+//! use ao3_api_rs::networking::{Ao3Client, get_page};
+//! use ao3_api_rs::testing::{MockServer, Reply};
+//! let server = MockServer::start()
+//!     .with_route("GET /works/1", Reply::status(503))
+//!     .with_route("GET /works/1", Reply::ok("<html></html>"));
+//! let client = Ao3Client::new(server.client_config()).unwrap();
+//! let page = get_page(&server.url("/works/1"), &client).unwrap();
+//! assert_eq!(page.status(), 200);
+//! ```
+use crate::networking::{ClientConfig, RetryPolicy};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Replies waiting for each "METHOD /path" route
+type Routes = HashMap<String, VecDeque<Reply>>;
+
+/// Path of a file in the fixtures directory
+pub fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+/// Read a file from the fixtures directory
+///
+/// # Panics
+/// * panics when the fixture doesn't exist, a missing fixture is a broken test
+pub fn fixture(name: &str) -> String {
+    let path = fixture_path(name);
+    fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("Failed to read fixture {}: {}", path.display(), err))
+}
+
+/// A canned response
+#[derive(Debug, Clone)]
+pub struct Reply {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Reply {
+    /// 200 with an HTML body
+    pub fn ok(body: &str) -> Self {
+        Self::status(200)
+            .with_header("Content-Type", "text/html; charset=utf-8")
+            .with_body(body)
+    }
+
+    /// 200 with a JSON body
+    pub fn json(body: &str) -> Self {
+        Self::status(200)
+            .with_header("Content-Type", "application/json; charset=utf-8")
+            .with_body(body)
+    }
+
+    /// 200 with the contents of a fixture, JSON fixtures get a JSON content type
+    pub fn fixture(name: &str) -> Self {
+        if name.ends_with(".json") {
+            Self::json(&fixture(name))
+        } else {
+            Self::ok(&fixture(name))
+        }
+    }
+
+    /// Empty response with `status`
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+
+    /// 302 to `location`
+    pub fn redirect(location: &str) -> Self {
+        Self::status(302).with_header("Location", location)
+    }
+
+    /// 429 with a `Retry-After` header in seconds
+    pub fn rate_limited(retry_after: u64) -> Self {
+        Self::status(429).with_header("Retry-After", &retry_after.to_string())
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_body(mut self, body: &str) -> Self {
+        self.body = body.to_string();
+        self
+    }
+}
+
+/// A request the server received
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    /// Path and query string, e.g. `/works/search?page=2`
+    pub path: String,
+    /// Header names are lowercase
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl Request {
+    /// "METHOD /path" without the query string, the key routes are matched on
+    pub fn route(&self) -> String {
+        let path = self.path.split('?').next().unwrap_or_default();
+        format!("{} {}", self.method, path)
+    }
+}
+
+/// Local HTTP server answering from a table of canned replies
+///
+/// Routes are "METHOD /path" without the query string. Adding several replies
+/// to one route serves them in order, and the last one keeps being served.
+/// Unknown routes get a 404. The server stops when it is dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    base_url: String,
+    routes: Arc<Mutex<Routes>>,
+    requests: Arc<Mutex<Vec<Request>>>,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Start a server on a free local port
+    ///
+    /// # Panics
+    /// * panics when no local port can be bound
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind a local port");
+        let base_url = format!(
+            "http://{}",
+            listener
+                .local_addr()
+                .expect("Bound listener has no address")
+        );
+        let routes = Arc::new(Mutex::new(Routes::new()));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let routes = routes.clone();
+            let requests = requests.clone();
+            let shutdown = shutdown.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    let Ok(request) = read_request(&stream) else {
+                        continue;
+                    };
+                    let reply = next_reply(&mut lock(&routes), &request.route());
+                    lock(&requests).push(request);
+                    let _ = write_reply(stream, &reply);
+                }
+            })
+        };
+
+        Self {
+            base_url,
+            routes,
+            requests,
+            shutdown,
+            handle: Some(handle),
+        }
+    }
+
+    /// Queue `reply` for `route`, e.g. `"GET /works/1"`
+    pub fn with_route(self, route: &str, reply: Reply) -> Self {
+        lock(&self.routes)
+            .entry(route.to_string())
+            .or_default()
+            .push_back(reply);
+        self
+    }
+
+    /// `http://127.0.0.1:port`, without a trailing slash
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Absolute URL of `path` on this server
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    /// Client settings pointed at this server, without rate limiting or long retry waits
+    pub fn client_config(&self) -> ClientConfig {
+        ClientConfig::new("ao3-api-rs tests")
+            .with_base_url(&self.base_url)
+            .with_rate_limit(0, 1)
+            .with_retry(
+                RetryPolicy::default()
+                    .with_max_attempts(3)
+                    .with_base_delay(Duration::from_millis(1))
+                    .with_max_delay(Duration::from_millis(10))
+                    .with_jitter(false),
+            )
+    }
+
+    /// Every request received so far, in order
+    pub fn requests(&self) -> Vec<Request> {
+        lock(&self.requests).clone()
+    }
+
+    /// Requests received for `route`, e.g. `"POST /users/login"`
+    pub fn received(&self, route: &str) -> Vec<Request> {
+        lock(&self.requests)
+            .iter()
+            .filter(|request| request.route() == route)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // wake the accept loop so it sees the flag
+        let _ = TcpStream::connect(self.base_url.trim_start_matches("http://"));
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Helper function to lock a mutex, a panicking test shouldn't poison the others
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Helper function to pop the next reply for `route`, keeping the last one
+fn next_reply(routes: &mut Routes, route: &str) -> Reply {
+    match routes.get_mut(route) {
+        Some(replies) if replies.len() > 1 => replies.pop_front().unwrap_or(Reply::status(404)),
+        Some(replies) => replies.front().cloned().unwrap_or(Reply::status(404)),
+        None => Reply::status(404),
+    }
+}
+
+fn read_request(stream: &TcpStream) -> io::Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            break;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let length = headers
+        .get("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Request {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+fn write_reply(mut stream: TcpStream, reply: &Reply) -> io::Result<()> {
+    let mut response = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+        reply.status,
+        reply.body.len()
+    );
+    for (name, value) in &reply.headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
+    response.push_str(&reply.body);
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
@@ -2,9 +2,9 @@
 
 use crate::errors::Ao3ApiError;
 use regex::Regex;
-use scraper::Selector;
+use reqwest::Url;
+use scraper::{CaseSensitivity, ElementRef, Node, Selector};
 use std::sync::LazyLock;
-use std::sync::{Arc, Mutex};
 
 /// Creates a selector from provided string
 ///
@@ -16,13 +16,59 @@ pub(crate) fn make_selector(
     Selector::parse(selector)
 }
 
-/// Wraps argument in Arc<Mutex<T>> for thread-safe shared ownership
+/// Resolve `href` against `base` the way a browser would
 ///
-/// Internal utility function for creating thread-safe shared state.
-#[inline(always)]
-pub(crate) fn arcify<T>(arg: T) -> Arc<Mutex<T>> {
-    Arc::new(Mutex::new(arg))
+/// Absolute hrefs replace the base entirely, root-relative ones keep only its
+/// scheme and host. Falls back to concatenation when `base` isn't a valid URL.
+pub(crate) fn join_url(base: &str, href: &str) -> String {
+    Url::parse(base)
+        .and_then(|base| base.join(href))
+        .map(String::from)
+        .unwrap_or_else(|_| format!("{}{}", base.trim_end_matches('/'), href))
+}
+
+/// Decodes `%XX` escapes in a URL path segment
+///
+/// Internal utility function, invalid escapes are kept as they are.
+pub(crate) fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| segment.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
 }
+
+/// Encodes a URL path segment, the inverse of [`percent_decode`]
+///
+/// Internal utility function, `*` is kept as is since AO3 uses it in tag URLs.
+pub(crate) fn percent_encode(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'*' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
 /// Macro to create a static LazyLock
 
 #[macro_export]
@@ -99,3 +145,343 @@ macro_rules! define_regex {
             make_static!({ Regex::new($text).ok() });
     };
 }
+
+/// Tags kept by [`sanitize_html`], everything else is unwrapped to its children
+const ALLOWED_TAGS: &[&str] = &[
+    "a",
+    "abbr",
+    "b",
+    "big",
+    "blockquote",
+    "br",
+    "caption",
+    "center",
+    "cite",
+    "code",
+    "dd",
+    "del",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "rp",
+    "rt",
+    "ruby",
+    "s",
+    "small",
+    "span",
+    "strike",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "u",
+    "ul",
+];
+/// Tags dropped by [`sanitize_html`] together with their contents
+const DROPPED_TAGS: &[&str] = &[
+    "script", "style", "iframe", "object", "embed", "form", "input", "button", "noscript",
+];
+/// Attributes kept by [`sanitize_html`]
+const ALLOWED_ATTRS: &[&str] = &[
+    "href", "src", "alt", "title", "width", "height", "align", "dir", "lang", "colspan", "rowspan",
+];
+/// Tags that start a new paragraph in [`html_to_text`]
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "div",
+    "blockquote",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "li",
+    "dl",
+    "dt",
+    "dd",
+    "hr",
+    "pre",
+    "table",
+    "tr",
+    "center",
+    "details",
+    "figure",
+];
+
+/// Escapes text for use in HTML text nodes and attribute values
+///
+/// Internal utility function for rebuilding markup.
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Returns true for AO3's screen-reader only headings such as "Chapter Text"
+fn is_landmark(element: &scraper::node::Element) -> bool {
+    element.has_class("landmark", CaseSensitivity::CaseSensitive)
+}
+
+/// Returns the inner HTML of `element` restricted to a safe set of tags and attributes
+///
+/// Scripts, styles, embeds and landmark headings are removed, unknown tags are
+/// unwrapped and void elements are self-closed so the result is also valid XHTML.
+pub(crate) fn sanitize_html(element: ElementRef<'_>) -> String {
+    let mut out = String::new();
+    write_sanitized_children(element, false, &mut out);
+    out.trim().to_string()
+}
+
+/// Like [`sanitize_html`], but the result is valid EPUB 3 content
+///
+/// EPUB 3 doesn't allow remote images, so images are replaced with their alt
+/// text, which links to the image unless it is already inside a link. The
+/// presentational markup HTML5 made obsolete is rewritten: `center` becomes a
+/// centered `div`, `big` a `span` of class `big`, `strike` becomes `s` and
+/// `align` turns into a `text-align` style. `width` and `height` are dropped.
+pub(crate) fn sanitize_html_epub(element: ElementRef<'_>) -> String {
+    let mut out = String::new();
+    write_sanitized_children(element, true, &mut out);
+    out.trim().to_string()
+}
+
+fn write_sanitized_children(element: ElementRef<'_>, epub: bool, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(&escape_html(text)),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    write_sanitized(child, epub, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Helper function to write an image's alt text in place of the image
+fn write_image_placeholder(element: ElementRef<'_>, out: &mut String) {
+    let src = element.value().attr("src").unwrap_or_default();
+    let label = match element.value().attr("alt").map(str::trim) {
+        Some(alt) if !alt.is_empty() => format!("[Image: {}]", alt),
+        _ => "[Image]".to_string(),
+    };
+    let in_link = element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .any(|ancestor| ancestor.value().name() == "a");
+    if in_link {
+        out.push_str(&escape_html(&label));
+    } else {
+        out.push_str(&format!(
+            "<a href=\"{}\">{}</a>",
+            escape_html(src),
+            escape_html(&label)
+        ));
+    }
+}
+
+fn write_sanitized(element: ElementRef<'_>, epub: bool, out: &mut String) {
+    let name = element.value().name();
+    if DROPPED_TAGS.contains(&name) || is_landmark(element.value()) {
+        return;
+    }
+    if !ALLOWED_TAGS.contains(&name) {
+        write_sanitized_children(element, epub, out);
+        return;
+    }
+    if name == "img" && epub {
+        write_image_placeholder(element, out);
+        return;
+    }
+
+    // EPUB 3 is HTML5, which has no presentational tags
+    let (tag, class, mut style) = match name {
+        "center" if epub => ("div", None, Some("text-align:center".to_string())),
+        "big" if epub => ("span", Some("big"), None),
+        "strike" if epub => ("s", None, None),
+        _ => (name, None, None),
+    };
+    out.push('<');
+    out.push_str(tag);
+    for (attr, value) in element.value().attrs() {
+        // no javascript: links sneaking through href/src
+        if !ALLOWED_ATTRS.contains(&attr)
+            || value
+                .trim_start()
+                .to_ascii_lowercase()
+                .starts_with("javascript:")
+        {
+            continue;
+        }
+        if epub && matches!(attr, "align" | "width" | "height") {
+            let align = value.trim().to_ascii_lowercase();
+            if attr == "align"
+                && style.is_none()
+                && matches!(align.as_str(), "left" | "right" | "center" | "justify")
+            {
+                style = Some(format!("text-align:{}", align));
+            }
+            continue;
+        }
+        out.push_str(&format!(" {}=\"{}\"", attr, escape_html(value)));
+    }
+    if let Some(class) = class {
+        out.push_str(&format!(" class=\"{}\"", class));
+    }
+    if let Some(style) = style {
+        out.push_str(&format!(" style=\"{}\"", style));
+    }
+    if matches!(tag, "br" | "hr" | "img") {
+        out.push_str(" />");
+        return;
+    }
+    out.push('>');
+    write_sanitized_children(element, epub, out);
+    out.push_str(&format!("</{}>", tag));
+}
+
+/// Elements serialized without a closing tag by [`html_without`]
+const VOID_TAGS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Serializes `element` like `ElementRef::html`, leaving out every descendant matching `skip`
+///
+/// Internal utility function for handing part of a blurb to another extractor.
+pub(crate) fn html_without(element: ElementRef<'_>, skip: &Selector) -> String {
+    let mut out = String::new();
+    write_without(element, skip, &mut out);
+    out
+}
+
+fn write_without(element: ElementRef<'_>, skip: &Selector, out: &mut String) {
+    let name = element.value().name();
+    out.push('<');
+    out.push_str(name);
+    for (attr, value) in element.value().attrs() {
+        out.push_str(&format!(" {}=\"{}\"", attr, escape_html(value)));
+    }
+    out.push('>');
+    if VOID_TAGS.contains(&name) {
+        return;
+    }
+    for child in element.children() {
+        match child.value() {
+            // script and style contents aren't escaped when parsed, so they mustn't be here either
+            Node::Text(text) if matches!(name, "script" | "style") => out.push_str(text),
+            Node::Text(text) => out.push_str(&escape_html(text)),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child)
+                    && !skip.matches(&child)
+                {
+                    write_without(child, skip, out);
+                }
+            }
+            _ => {}
+        }
+    }
+    out.push_str(&format!("</{}>", name));
+}
+
+/// Returns the text of `element` with paragraphs separated by blank lines
+///
+/// Internal utility function for turning userstuff blocks into plain text.
+pub(crate) fn html_to_text(element: ElementRef<'_>) -> String {
+    let mut raw = String::new();
+    write_text(element, &mut raw);
+
+    // trim every line and collapse runs of blank lines into one
+    let mut text = String::new();
+    let mut blank = false;
+    for line in raw.lines().map(str::trim) {
+        if line.is_empty() {
+            blank = !text.is_empty();
+            continue;
+        }
+        if blank {
+            text.push_str("\n\n");
+        } else if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(line);
+        blank = false;
+    }
+    text
+}
+
+fn write_text(element: ElementRef<'_>, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => {
+                // collapse HTML whitespace the way a browser would
+                let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                if text.starts_with(char::is_whitespace) && !out.ends_with([' ', '\n']) {
+                    out.push(' ');
+                }
+                out.push_str(&collapsed);
+                if text.ends_with(char::is_whitespace) && !collapsed.is_empty() {
+                    out.push(' ');
+                }
+            }
+            Node::Element(elem) => {
+                let Some(child) = ElementRef::wrap(child) else {
+                    continue;
+                };
+                let name = elem.name();
+                if DROPPED_TAGS.contains(&name) || is_landmark(elem) {
+                    continue;
+                }
+                if name == "br" {
+                    out.push('\n');
+                } else if BLOCK_TAGS.contains(&name) {
+                    out.push_str("\n\n");
+                    write_text(child, out);
+                    out.push_str("\n\n");
+                } else {
+                    write_text(child, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
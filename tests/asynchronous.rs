@@ -0,0 +1,116 @@
+#![cfg(feature = "async")]
+use ao3_api_rs::Ao3ApiError;
+use ao3_api_rs::networking::LoginInfo;
+use ao3_api_rs::networking::auth::asynchronous::login;
+use ao3_api_rs::networking::client::asynchronous::{Ao3Client, get_page};
+use ao3_api_rs::testing::{MockServer, Reply};
+use reqwest::Url;
+use std::collections::HashMap;
+
+fn form_fields(body: &str) -> HashMap<String, String> {
+    Url::parse(&format!("http://localhost/?{}", body))
+        .unwrap()
+        .query_pairs()
+        .into_owned()
+        .collect()
+}
+
+#[tokio::test]
+async fn get_page_follows_redirects() {
+    let server = MockServer::start()
+        .with_route(
+            "GET /works/1002",
+            Reply::redirect("/works/1002/chapters/2001"),
+        )
+        .with_route("GET /works/1002/chapters/2001", Reply::fixture("work.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let page = get_page(&server.url("/works/1002"), &client).await.unwrap();
+    assert_eq!(page.url().path(), "/works/1002/chapters/2001");
+    assert_eq!(page.redirects, vec![server.url("/works/1002")]);
+}
+
+#[tokio::test]
+async fn get_page_reports_redirect_cycle() {
+    let server = MockServer::start()
+        .with_route("GET /a", Reply::redirect("/b"))
+        .with_route("GET /b", Reply::redirect("/a"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let err = get_page(&server.url("/a"), &client).await.unwrap_err();
+    match err {
+        Ao3ApiError::RedirectLoop(chain) => assert_eq!(
+            chain,
+            vec![server.url("/a"), server.url("/b"), server.url("/a")]
+        ),
+        other => panic!("expected RedirectLoop, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn get_page_retries_unavailable() {
+    let server = MockServer::start()
+        .with_route("GET /works/1002", Reply::status(503))
+        .with_route("GET /works/1002", Reply::rate_limited(0))
+        .with_route("GET /works/1002", Reply::fixture("work.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let page = get_page(&server.url("/works/1002"), &client).await.unwrap();
+    assert_eq!(page.status(), 200);
+    assert_eq!(server.received("GET /works/1002").len(), 3);
+}
+
+#[tokio::test]
+async fn get_page_gives_up_when_rate_limited() {
+    let server = MockServer::start().with_route("GET /works/1002", Reply::rate_limited(0));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    match get_page(&server.url("/works/1002"), &client)
+        .await
+        .unwrap_err()
+    {
+        Ao3ApiError::RateLimited { .. } => {}
+        other => panic!("expected RateLimited, got {:?}", other),
+    }
+    assert_eq!(server.received("GET /works/1002").len(), 3);
+}
+
+#[tokio::test]
+async fn login_encodes_form() {
+    let server = MockServer::start()
+        .with_route(
+            "GET /token_dispenser.json",
+            Reply::fixture("token_dispenser.json"),
+        )
+        .with_route("POST /users/login", Reply::redirect("/users/reader"))
+        .with_route("GET /users/reader", Reply::fixture("dashboard.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let session = login(&client, LoginInfo::new("reader", "p&ss+w%rd=1 x"))
+        .await
+        .unwrap();
+    assert_eq!(session.username, "reader");
+
+    let posted = server.received("POST /users/login");
+    assert_eq!(posted.len(), 1);
+    let fields = form_fields(&posted[0].body);
+    assert_eq!(fields["authenticity_token"], "dispensed+token/value==");
+    assert_eq!(fields["user[login]"], "reader");
+    assert_eq!(fields["user[password]"], "p&ss+w%rd=1 x");
+}
+
+#[tokio::test]
+async fn login_reports_rejection() {
+    let server = MockServer::start()
+        .with_route(
+            "GET /token_dispenser.json",
+            Reply::fixture("token_dispenser.json"),
+        )
+        .with_route("POST /users/login", Reply::redirect("/users/login"))
+        .with_route("GET /users/login", Reply::fixture("login_failed.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let err = login(&client, LoginInfo::new("reader", "wrong"))
+        .await
+        .unwrap_err();
+    match err {
+        Ao3ApiError::AuthFailed(message) => {
+            assert!(message.contains("doesn't match our records"))
+        }
+        other => panic!("expected AuthFailed, got {:?}", other),
+    }
+}
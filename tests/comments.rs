@@ -0,0 +1,81 @@
+use ao3_api_rs::extraction::{Comment, CommentAuthor, extract_comments};
+use ao3_api_rs::networking::{Ao3Client, CommentListing};
+use ao3_api_rs::testing::{MockServer, Reply, fixture};
+use scraper::Html;
+
+#[test]
+fn comment_tree() {
+    let document = Html::parse_document(&fixture("comments_page_1.html"));
+    let comments = extract_comments(&document).unwrap();
+    let ids: Vec<&str> = comments.iter().map(|comment| comment.id.as_str()).collect();
+    assert_eq!(ids, vec!["111", "113"]);
+
+    let first = &comments[0];
+    assert!(matches!(&first.author, Some(CommentAuthor::User(pseud)) if pseud.user == "reader"));
+    assert_eq!(first.chapter, Some(1));
+    assert_eq!(first.posted.as_deref(), Some("Tue 20 Feb 2024 09:15PM UTC"));
+    assert_eq!(first.text, "Loved the motel scene!\n\nCan't wait for more.");
+    assert!(first.edited);
+    assert_eq!(first.parent_id, None);
+    assert_eq!(first.thread_len(), 3);
+
+    let reply = &first.replies[0];
+    assert_eq!(reply.id, "112");
+    assert_eq!(reply.parent_id.as_deref(), Some("111"));
+    assert!(!reply.edited);
+    let nested = &reply.replies[0];
+    assert_eq!(nested.parent_id.as_deref(), Some("112"));
+    assert_eq!(
+        nested.author,
+        Some(CommentAuthor::Guest("Passerby".to_string()))
+    );
+
+    let collapsed = &comments[1];
+    assert_eq!(collapsed.chapter, Some(2));
+    assert!(collapsed.html.contains("<em>wrecked</em>"));
+    assert!(collapsed.replies.is_empty());
+    assert_eq!(
+        collapsed.thread_url.as_deref(),
+        Some("https://archiveofourown.org/comments/113")
+    );
+}
+
+#[test]
+fn comment_listing_follows_pages_and_threads() {
+    let server = MockServer::start()
+        .with_route(
+            "GET /works/1002/comments",
+            Reply::fixture("comments_page_1.html"),
+        )
+        .with_route(
+            "GET /works/1002/comments",
+            Reply::fixture("comments_page_2.html"),
+        )
+        .with_route("GET /comments/113", Reply::fixture("comment_thread.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let pages: Vec<Vec<Comment>> = CommentListing::new("/works/1002/comments", &client)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(pages.len(), 2);
+
+    let expanded = &pages[0][1];
+    assert_eq!(expanded.thread_url, None);
+    let replies: Vec<&str> = expanded
+        .replies
+        .iter()
+        .map(|reply| reply.id.as_str())
+        .collect();
+    assert_eq!(replies, vec!["116", "117"]);
+    assert!(
+        expanded
+            .replies
+            .iter()
+            .all(|reply| reply.parent_id.as_deref() == Some("113"))
+    );
+    assert_eq!(pages[1][0].id, "120");
+    assert_eq!(server.received("GET /comments/113").len(), 1);
+    assert_eq!(
+        server.received("GET /works/1002/comments")[1].path,
+        "/works/1002/comments?page=2"
+    );
+}
@@ -0,0 +1,92 @@
+use ao3_api_rs::Ao3ApiError;
+use ao3_api_rs::networking::auth::CredentialSource;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+/// Helper function to write a login file unique to this test run
+fn login_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("ao3-api-rs-{}-{}.txt", name, std::process::id()));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn file_holds_username_then_password() {
+    let path = login_file("login", "reader\np@ss word+&=\n");
+    let info = CredentialSource::from(path.clone()).resolve().unwrap();
+    assert_eq!(&*info.username, "reader");
+    assert_eq!(&*info.password, "p@ss word+&=");
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn file_without_password_is_rejected() {
+    let path = login_file("login-short", "reader\n");
+    let err = CredentialSource::File(path.clone()).resolve().unwrap_err();
+    assert!(
+        matches!(&err, Ao3ApiError::IoError(io) if io.kind() == ErrorKind::InvalidData),
+        "{:?}",
+        err
+    );
+    assert!(err.to_string().contains("Password not found"), "{}", err);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn env_reads_the_named_variables() {
+    let username = format!("AO3_API_RS_TEST_USER_{}", std::process::id());
+    let password = format!("AO3_API_RS_TEST_PASS_{}", std::process::id());
+    // SAFETY: the variable names are unique to this test, nothing else reads them
+    unsafe {
+        std::env::set_var(&username, "reader");
+        std::env::set_var(&password, "secret");
+    }
+    let info = CredentialSource::Env {
+        username: username.clone(),
+        password: password.clone(),
+    }
+    .resolve()
+    .unwrap();
+    assert_eq!(&*info.username, "reader");
+    assert_eq!(&*info.password, "secret");
+
+    // SAFETY: as above
+    unsafe { std::env::remove_var(&password) };
+    let err = CredentialSource::Env {
+        username,
+        password: password.clone(),
+    }
+    .resolve()
+    .unwrap_err();
+    assert!(
+        matches!(&err, Ao3ApiError::AuthFailed(message) if message.contains(&password)),
+        "{:?}",
+        err
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn secret_file_must_be_private() {
+    use std::os::unix::fs::PermissionsExt;
+    let path = login_file("secret", "reader\nsecret\n");
+    for mode in [0o644, 0o640, 0o604, 0o660] {
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        let err = CredentialSource::SecretFile(path.clone())
+            .resolve()
+            .unwrap_err();
+        assert!(
+            matches!(&err, Ao3ApiError::IoError(io) if io.kind() == ErrorKind::PermissionDenied),
+            "{:o}: {:?}",
+            mode,
+            err
+        );
+    }
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+    let info = CredentialSource::SecretFile(path.clone())
+        .resolve()
+        .unwrap();
+    assert_eq!(&*info.username, "reader");
+    fs::remove_file(path).unwrap();
+}
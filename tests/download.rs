@@ -0,0 +1,88 @@
+use ao3_api_rs::download::{
+    DownloadFormat, download_links, download_to_writer, download_work, extract_download_links,
+};
+use ao3_api_rs::networking::Ao3Client;
+use ao3_api_rs::testing::{MockServer, Reply, fixture};
+use scraper::Html;
+use std::fs;
+
+#[test]
+fn finds_every_format() {
+    let document = Html::parse_document(&fixture("work.html"));
+    let links = extract_download_links(&document).unwrap();
+    let formats: Vec<DownloadFormat> = links.iter().map(|link| link.format).collect();
+    assert_eq!(formats, DownloadFormat::ALL.to_vec());
+    assert_eq!(
+        links[1].url,
+        "https://archiveofourown.org/downloads/1002/The%20Long%20Way%20Home.epub?updated_at=1708128000"
+    );
+    assert_eq!(links[1].filename(), "The Long Way Home.epub");
+}
+
+#[test]
+fn streams_to_writer() {
+    let server = MockServer::start()
+        .with_route("GET /works/1002", Reply::fixture("work.html"))
+        .with_route(
+            "GET /downloads/1002/The%20Long%20Way%20Home.pdf",
+            Reply::status(200)
+                .with_header("Content-Type", "application/pdf")
+                .with_body("%PDF-1.4 stand-in"),
+        );
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let links = download_links("/works/1002", &client).unwrap();
+    let pdf = links
+        .iter()
+        .find(|link| link.format == DownloadFormat::Pdf)
+        .unwrap();
+    assert!(pdf.url.starts_with(server.base_url()));
+
+    let mut buffer = Vec::new();
+    let download = download_to_writer(pdf, &client, &mut buffer).unwrap();
+    assert_eq!(buffer, b"%PDF-1.4 stand-in");
+    assert_eq!(download.size, buffer.len() as u64);
+    assert_eq!(download.content_type.as_deref(), Some("application/pdf"));
+    assert_eq!(download.path, None);
+}
+
+#[test]
+fn saves_to_file() {
+    let server = MockServer::start()
+        .with_route("GET /works/1002", Reply::fixture("work.html"))
+        .with_route(
+            "GET /downloads/1002/The%20Long%20Way%20Home.epub",
+            Reply::status(200)
+                .with_header("Content-Type", "application/epub+zip")
+                .with_body("PK stand-in"),
+        );
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let dir = std::env::temp_dir().join(format!("ao3-api-rs-download-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let download = download_work("/works/1002", DownloadFormat::Epub, &client, &dir).unwrap();
+    let path = download.path.unwrap();
+    assert_eq!(path, dir.join("The Long Way Home.epub"));
+    assert_eq!(fs::read(&path).unwrap(), b"PK stand-in");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failed_download_keeps_earlier_copy() {
+    let server = MockServer::start()
+        .with_route("GET /works/1002", Reply::fixture("work.html"))
+        .with_route(
+            "GET /downloads/1002/The%20Long%20Way%20Home.epub",
+            Reply::status(500),
+        );
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let dir = std::env::temp_dir().join(format!("ao3-api-rs-redownload-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("The Long Way Home.epub");
+    fs::write(&path, "earlier copy").unwrap();
+
+    assert!(download_work("/works/1002", DownloadFormat::Epub, &client, &dir).is_err());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "earlier copy");
+    // no partial file is left behind either
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,102 @@
+use ao3_api_rs::epub::EpubWriter;
+use ao3_api_rs::extraction::{extract_chapters, extract_work_page};
+use ao3_api_rs::testing::fixture;
+use scraper::Html;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Entries are stored uncompressed, so their contents can be searched for directly
+fn contains(haystack: &[u8], needle: &str) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle.as_bytes())
+}
+
+fn work_epub() -> Vec<u8> {
+    let document = Html::parse_document(&fixture("work.html"));
+    let work = extract_work_page(&document).unwrap();
+    let chapters = extract_chapters(&document).unwrap();
+    EpubWriter::from_work(work, chapters)
+        .with_modified(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        .write_to(Vec::new())
+        .unwrap()
+}
+
+#[test]
+fn epub_starts_with_stored_mimetype() {
+    let epub = work_epub();
+    assert_eq!(&epub[..4], b"PK\x03\x04");
+    // compression method 0, then the name and contents of the first entry
+    assert_eq!(&epub[8..10], &[0, 0]);
+    assert_eq!(&epub[30..38], b"mimetype");
+    assert_eq!(&epub[38..58], b"application/epub+zip");
+}
+
+#[test]
+fn epub_contains_chapters_and_metadata() {
+    let epub = work_epub();
+    assert!(contains(&epub, "OEBPS/nav.xhtml"));
+    assert!(contains(&epub, "OEBPS/chapter-1.xhtml"));
+    assert!(contains(&epub, "OEBPS/chapter-2.xhtml"));
+    assert!(!contains(&epub, "OEBPS/chapter-3.xhtml"));
+    assert!(contains(&epub, "<dc:title>The Long Way Home</dc:title>"));
+    assert!(contains(&epub, ">roadtripper</dc:creator>"));
+    assert!(contains(&epub, "<dc:language>en</dc:language>"));
+    assert!(contains(
+        &epub,
+        "<meta property=\"dcterms:modified\">2023-11-14T22:13:20Z</meta>"
+    ));
+    assert!(contains(&epub, "Chapter 2: Arrival"));
+    assert!(contains(&epub, "old coffee"));
+}
+
+#[test]
+fn epub_replaces_remote_images() {
+    let document = Html::parse_document(&fixture("work.html"));
+    let work = extract_work_page(&document).unwrap();
+    let mut chapters = extract_chapters(&document).unwrap();
+    chapters[0].html.push_str(
+        "<p><img src=\"https://i.example.org/map.png\" alt=\"Map &amp; route\" /></p><p><a href=\"https://example.org\"><img src=\"https://i.example.org/logo.png\" /></a></p>",
+    );
+    let epub = EpubWriter::from_work(work, chapters)
+        .write_to(Vec::new())
+        .unwrap();
+    assert!(!contains(&epub, "<img"));
+    assert!(!contains(&epub, "remote-resources"));
+    assert!(contains(
+        &epub,
+        "<a href=\"https://i.example.org/map.png\">[Image: Map &amp; route]</a>"
+    ));
+    assert!(contains(
+        &epub,
+        "<a href=\"https://example.org\">[Image]</a>"
+    ));
+}
+
+#[test]
+fn epub_rewrites_presentational_markup() {
+    let document = Html::parse_document(&fixture("work.html"));
+    let work = extract_work_page(&document).unwrap();
+    let mut chapters = extract_chapters(&document).unwrap();
+    chapters[0].html.push_str(
+        "<center>The End</center><p align=\"RIGHT\"><big>Loud</big> and <strike>gone</strike></p><table width=\"80%\"><tr><td align=\"middle\">cell</td></tr></table>",
+    );
+    let epub = EpubWriter::from_work(work, chapters)
+        .write_to(Vec::new())
+        .unwrap();
+    for obsolete in ["<center", "<big", "<strike", "align=", "width="] {
+        assert!(!contains(&epub, obsolete), "{}", obsolete);
+    }
+    assert!(contains(
+        &epub,
+        "<div style=\"text-align:center\">The End</div>"
+    ));
+    assert!(contains(
+        &epub,
+        "<p style=\"text-align:right\"><span class=\"big\">Loud</span> and <s>gone</s></p>"
+    ));
+    assert!(contains(
+        &epub,
+        "<table><tbody><tr><td>cell</td></tr></tbody></table>"
+    ));
+    assert!(contains(&epub, "span.big { font-size: larger; }"));
+}
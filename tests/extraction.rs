@@ -0,0 +1,305 @@
+use ao3_api_rs::extraction::{
+    Gate, SeriesMembership, Tag, TagCategory, TagType, detect_gate, extract_chapters,
+    extract_listing, extract_listing_info, extract_series_page, extract_tag_page,
+    extract_typed_tags, extract_work_page, gettags, tags_from_map, tags_to_map,
+};
+use ao3_api_rs::testing::fixture;
+use scraper::Html;
+
+#[test]
+fn work_page_metadata() {
+    let document = Html::parse_document(&fixture("work.html"));
+    let work = extract_work_page(&document).unwrap();
+    assert_eq!(work.metadata.id, "1002");
+    assert_eq!(work.metadata.name, "The Long Way Home");
+    assert_eq!(work.metadata.authors, vec!["roadtripper"]);
+    assert_eq!(work.metadata.words, Some(21044));
+    assert_eq!(work.metadata.kudos, Some(1024));
+    assert_eq!(work.metadata.series, vec!["Part 2 of Highway Stories"]);
+    assert_eq!(work.bookmarks, Some(153));
+    assert_eq!(work.comments, Some(87));
+    assert_eq!(work.completed.as_deref(), Some("2024-02-17"));
+    assert_eq!(
+        work.metadata.tags.get("freeforms"),
+        Some(&vec!["Road Trips".to_string(), "Slow Burn".to_string()])
+    );
+}
+
+#[test]
+fn work_page_chapters() {
+    let document = Html::parse_document(&fixture("work.html"));
+    let chapters = extract_chapters(&document).unwrap();
+    assert_eq!(chapters.len(), 2);
+    assert_eq!(chapters[0].id.as_deref(), Some("2001"));
+    assert_eq!(chapters[0].title.as_deref(), Some("Departure"));
+    assert_eq!(
+        chapters[0].summary.as_deref(),
+        Some("They leave before sunrise.")
+    );
+    assert_eq!(chapters[0].end_notes.as_deref(), Some("More soon!"));
+    assert!(chapters[0].text.contains("old coffee"));
+    assert!(!chapters[0].text.contains("Chapter Text"));
+    assert_eq!(chapters[1].number, 2);
+    assert_eq!(chapters[1].title.as_deref(), Some("Arrival"));
+}
+
+#[test]
+fn search_listing_info() {
+    let document = Html::parse_document(&fixture("search_page_1.html"));
+    let info = extract_listing_info(&document).unwrap();
+    assert_eq!(info.first, Some(1));
+    assert_eq!(info.last, Some(2));
+    assert_eq!(info.total, Some(3));
+    assert_eq!(info.current_page, 1);
+    assert_eq!(info.last_page, 2);
+}
+
+#[test]
+fn blurb_without_kudos_has_zero_kudos() {
+    // AO3 leaves the kudos stat out of the blurb until a work gets its first kudos
+    let page = fixture("search_page_2.html").replace(
+        r#"<dd class="kudos"><a href="/works/1003#kudos">45</a></dd>"#,
+        "",
+    );
+    let fics = extract_listing(&Html::parse_document(&page));
+    let fic = fics[0].as_ref().unwrap();
+    assert_eq!(fic.id, "1003");
+    assert_eq!(fic.kudos, Some(0));
+}
+
+#[test]
+fn work_page_series_membership() {
+    let document = Html::parse_document(&fixture("work.html"));
+    let work = extract_work_page(&document).unwrap();
+    assert_eq!(
+        work.series,
+        vec![SeriesMembership {
+            id: "77".to_string(),
+            title: "Highway Stories".to_string(),
+            url: "https://archiveofourown.org/series/77".to_string(),
+            part: Some(2),
+        }]
+    );
+    assert_eq!(work.metadata.series, vec!["Part 2 of Highway Stories"]);
+}
+
+#[test]
+fn series_page() {
+    let document = Html::parse_document(&fixture("series.html"));
+    let series = extract_series_page(&document).unwrap();
+    assert_eq!(series.id, "77");
+    assert_eq!(series.title, "Highway Stories");
+    assert_eq!(series.creators, vec!["roadtripper"]);
+    assert_eq!(series.begun.as_deref(), Some("2023-12-02"));
+    assert_eq!(series.updated.as_deref(), Some("2024-02-17"));
+    assert_eq!(
+        series.description.as_deref(),
+        Some("Stories from the road.\n\nRead in any order.")
+    );
+    assert_eq!(series.notes.as_deref(), Some("Part three is planned."));
+    assert_eq!(series.words, Some(24164));
+    assert_eq!(series.works_count, Some(2));
+    assert_eq!(series.complete, Some(false));
+    assert_eq!(series.bookmarks, Some(31));
+    let ids: Vec<&str> = series
+        .works
+        .iter()
+        .map(|work| work.as_ref().unwrap().id.as_str())
+        .collect();
+    assert_eq!(ids, vec!["1003", "1002"]);
+    assert_eq!(
+        series.works[0].as_ref().unwrap().series,
+        vec!["Part 1 of Highway Stories"]
+    );
+}
+
+#[test]
+fn series_page_keeps_going_past_a_bad_blurb() {
+    let page = fixture("series.html").replacen(
+        "<ul class=\"series work index group\">",
+        "<ul class=\"series work index group\"><li class=\"work blurb group\" role=\"article\"><p>Broken</p></li>",
+        1,
+    );
+    let series = extract_series_page(&Html::parse_document(&page)).unwrap();
+    assert_eq!(series.works.len(), 3);
+    assert!(series.works[0].is_err());
+    assert!(series.works[1..].iter().all(Result::is_ok));
+}
+
+#[test]
+fn tag_page() {
+    let document = Html::parse_document(&fixture("tag.html"));
+    let tag = extract_tag_page(&document).unwrap();
+    assert_eq!(tag.name, "Hurt/Comfort");
+    assert_eq!(tag.url, "https://archiveofourown.org/tags/Hurt*s*Comfort");
+    assert_eq!(
+        tag.works_url,
+        "https://archiveofourown.org/tags/Hurt*s*Comfort/works"
+    );
+    assert_eq!(tag.tag_type, Some(TagType::Work(TagCategory::Freeform)));
+    assert!(tag.canonical);
+    assert_eq!(tag.merger, None);
+    assert_eq!(tag.canonical_name(), "Hurt/Comfort");
+    assert_eq!(tag.parents, vec!["No Fandom"]);
+    assert_eq!(
+        tag.synonyms,
+        vec!["h/c", "Hurt and Comfort", "hurt & comfort"]
+    );
+    assert_eq!(tag.metatags, vec!["Angst with a Happy Ending"]);
+    assert_eq!(tag.subtags, vec!["Whump", "Emotional Whump", "Sickfic"]);
+}
+
+#[test]
+fn tag_page_synonym() {
+    let document = Html::parse_document(
+        r#"<div id="main" class="tags-show region">
+        <div class="primary header module"><h2 class="heading">h/c</h2></div>
+        <div class="tag home profile">
+          <p>This tag belongs to the Additional Tags Category.</p>
+          <div class="merger module">
+            <p>h/c has been made a synonym of <a class="tag" href="/tags/Hurt*s*Comfort">Hurt/Comfort</a>.
+            Works and bookmarks tagged with h/c will show up in Hurt/Comfort's filter.</p>
+          </div>
+        </div></div>"#,
+    );
+    let tag = extract_tag_page(&document).unwrap();
+    assert!(!tag.canonical);
+    assert_eq!(tag.merger.as_deref(), Some("Hurt/Comfort"));
+    assert_eq!(tag.canonical_name(), "Hurt/Comfort");
+    assert_eq!(tag.url, "https://archiveofourown.org/tags/h*s*c");
+}
+
+#[test]
+fn typed_tags_round_trip() {
+    let document = Html::parse_document(&fixture("work.html"));
+    let work = extract_work_page(&document).unwrap();
+    let tags = tags_from_map(&work.metadata.tags);
+    assert_eq!(
+        tags[0],
+        Tag::new(TagCategory::Rating, "Teen And Up Audiences")
+    );
+    let relationship = tags
+        .iter()
+        .find(|tag| tag.category == TagCategory::Relationship)
+        .unwrap();
+    assert_eq!(relationship.name, "Alex/Sam");
+    assert_eq!(relationship.path(), "/tags/Alex*s*Sam");
+    assert_eq!(tags.last().map(|tag| tag.name.as_str()), Some("Slow Burn"));
+    assert_eq!(tags_to_map(&tags), work.metadata.tags);
+
+    let mut odd = work.metadata.tags.clone();
+    odd.insert(
+        "warnings last".to_string(),
+        vec!["Major Character Death".to_string()],
+    );
+    odd.insert("stats".to_string(), vec!["ignored".to_string()]);
+    let warnings: Vec<Tag> = tags_from_map(&odd)
+        .into_iter()
+        .filter(|tag| tag.category == TagCategory::Warning)
+        .collect();
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(
+        TagType::from(TagCategory::Freeform).category(),
+        Some(TagCategory::Freeform)
+    );
+    assert_eq!(TagType::Media.category(), None);
+}
+
+#[test]
+fn tag_slugs_come_from_links() {
+    let document = Html::parse_document(&fixture("work.html"));
+    let work = extract_work_page(&document).unwrap();
+    let slugs: Vec<&str> = work.tags.iter().map(|tag| tag.slug.as_str()).collect();
+    assert_eq!(
+        slugs,
+        vec![
+            "Teen%20And%20Up%20Audiences",
+            "No%20Archive%20Warnings%20Apply",
+            "M*s*M",
+            "Original%20Work",
+            "Alex*s*Sam",
+            "Alex",
+            "Sam",
+            "Road%20Trips",
+            "Slow%20Burn",
+        ]
+    );
+    assert_eq!(tags_to_map(&work.tags), work.metadata.tags);
+
+    let blurb = Html::parse_fragment(
+        r#"<ul class="tags commas">
+        <li class="relationships"><a class="tag" href="https://archiveofourown.org/tags/Alex*s*Sam%20(Highway)/works?page=2">Alex/Sam</a></li>
+        <li class="freeforms"><a class="tag">Tea &amp; Biscuits</a></li>
+        <li class="stats"><a class="tag" href="/tags/Ignored/works">Ignored</a></li>
+        </ul>"#,
+    );
+    let tags = extract_typed_tags(&blurb).unwrap();
+    assert_eq!(tags.len(), 2);
+    // the link decides the slug, not the displayed name
+    assert_eq!(tags[0].category, TagCategory::Relationship);
+    assert_eq!(tags[0].name, "Alex/Sam");
+    assert_eq!(tags[0].slug, "Alex*s*Sam%20(Highway)");
+    // without a link the slug is derived from the name
+    assert_eq!(tags[1], Tag::new(TagCategory::Freeform, "Tea & Biscuits"));
+}
+
+#[test]
+fn gettags_parses_markup_the_regex_missed() {
+    let blurb = r#"<li class="work blurb group" role="article">
+    <ul class="tags commas">
+      <li class='warnings'><strong><a href="/tags/Creator%20Chose%20Not%20To%20Use%20Archive%20Warnings/works"
+          class="tag">Creator Chose Not To Use
+          Archive Warnings</a></strong></li>
+      <li class="relationships"><a class="tag" href="/tags/Alex*s*Sam/works">Alex/Sam</a></li>
+      <li class="freeforms last"><a class="tag" href="/tags/Tea%20*a*%20Biscuits/works">Tea &amp; <em>Biscuits</em></a></li>
+      <li class="freeforms"><a class="tag" href="/tags/Fluff/works">Fluff</a></li>
+    </ul>
+    </li>"#;
+    let tags = gettags(blurb.to_string()).unwrap();
+    assert_eq!(
+        tags["warnings"],
+        vec!["Creator Chose Not To Use Archive Warnings"]
+    );
+    assert_eq!(tags["relationships"], vec!["Alex/Sam"]);
+    assert_eq!(tags["freeforms"], vec!["Tea & Biscuits", "Fluff"]);
+    assert_eq!(tags.len(), 3);
+}
+
+#[test]
+fn gate_pages() {
+    let cases = [
+        ("gate_adult.html", Gate::AdultContent),
+        ("gate_locked.html", Gate::LoginRequired),
+        ("gate_hidden.html", Gate::HiddenByAdmin),
+        ("gate_deleted.html", Gate::Deleted),
+        ("gate_404.html", Gate::NotFound),
+    ];
+    for (name, gate) in cases {
+        let document = Html::parse_document(&fixture(name));
+        assert_eq!(detect_gate(&document), Some(gate), "{}", name);
+    }
+    for name in ["work.html", "series.html", "search_page_1.html"] {
+        assert_eq!(detect_gate(&Html::parse_document(&fixture(name))), None);
+    }
+}
+
+#[test]
+fn titles_are_not_gate_notices() {
+    for title in [
+        "This Story Has Been Deleted",
+        "The Door That Has Been Hidden",
+        "Only Available To Registered Users",
+    ] {
+        let title = title.to_lowercase();
+        let work = fixture("work.html").replace("The Long Way Home\n", &format!("{}\n", title));
+        let work = extract_work_page(&Html::parse_document(&work)).unwrap();
+        assert_eq!(work.metadata.name, title);
+
+        let series = fixture("series.html").replace(
+            "<h2 class=\"heading\">Highway Stories</h2>",
+            &format!("<h2 class=\"heading\">{}</h2>", title),
+        );
+        let series = extract_series_page(&Html::parse_document(&series)).unwrap();
+        assert_eq!(series.title, title);
+    }
+}
@@ -0,0 +1,196 @@
+use ao3_api_rs::Ao3ApiError;
+use ao3_api_rs::networking::{
+    Ao3Client, CookieJar, LoginInfo, Session, current_session, is_logged_in, login, logout,
+    resume_session,
+};
+use ao3_api_rs::testing::{MockServer, Reply};
+use reqwest::Url;
+use reqwest::cookie::CookieStore;
+use reqwest::header::HeaderValue;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+fn form_fields(body: &str) -> HashMap<String, String> {
+    Url::parse(&format!("http://localhost/?{}", body))
+        .unwrap()
+        .query_pairs()
+        .into_owned()
+        .collect()
+}
+
+/// Helper function to get a cookie file path unique to this test run
+fn cookie_file(name: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("ao3-api-rs-{}-{}.json", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+/// Helper function to save a jar holding `cookies` for `server`
+fn save_jar(server: &MockServer, path: &PathBuf, cookies: &[&'static str]) {
+    let jar = CookieJar::new();
+    let url = Url::parse(&server.url("/")).unwrap();
+    let headers: Vec<HeaderValue> = cookies
+        .iter()
+        .map(|cookie| HeaderValue::from_static(cookie))
+        .collect();
+    jar.set_cookies(&mut headers.iter(), &url);
+    jar.save(path).unwrap();
+}
+
+#[test]
+fn login_encodes_form() {
+    let server = MockServer::start()
+        .with_route(
+            "GET /token_dispenser.json",
+            Reply::fixture("token_dispenser.json"),
+        )
+        .with_route("POST /users/login", Reply::redirect("/users/reader"))
+        .with_route("GET /users/reader", Reply::fixture("dashboard.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let session = login(&client, LoginInfo::new("reader", "p&ss+w%rd=1 x")).unwrap();
+    assert_eq!(session.username, "reader");
+
+    let posted = server.received("POST /users/login");
+    assert_eq!(posted.len(), 1);
+    assert_eq!(
+        posted[0].headers.get("content-type").map(String::as_str),
+        Some("application/x-www-form-urlencoded")
+    );
+    let fields = form_fields(&posted[0].body);
+    assert_eq!(fields["authenticity_token"], "dispensed+token/value==");
+    assert_eq!(fields["user[login]"], "reader");
+    assert_eq!(fields["user[password]"], "p&ss+w%rd=1 x");
+}
+
+#[test]
+fn login_falls_back_to_form_token() {
+    let server = MockServer::start()
+        .with_route("GET /token_dispenser.json", Reply::status(404))
+        .with_route("GET /users/login", Reply::fixture("login.html"))
+        .with_route("POST /users/login", Reply::redirect("/users/reader"))
+        .with_route("GET /users/reader", Reply::fixture("dashboard.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    login(&client, LoginInfo::new("reader", "secret")).unwrap();
+
+    let posted = server.received("POST /users/login");
+    let fields = form_fields(&posted[0].body);
+    assert_eq!(fields["authenticity_token"], "form+token/value==");
+}
+
+#[test]
+fn login_reports_rejection() {
+    let server = MockServer::start()
+        .with_route(
+            "GET /token_dispenser.json",
+            Reply::fixture("token_dispenser.json"),
+        )
+        .with_route("POST /users/login", Reply::redirect("/users/login"))
+        .with_route("GET /users/login", Reply::fixture("login_failed.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let err = login(&client, LoginInfo::new("reader", "wrong")).unwrap_err();
+    match err {
+        Ao3ApiError::AuthFailed(message) => {
+            assert!(message.contains("doesn't match our records"))
+        }
+        other => panic!("expected AuthFailed, got {:?}", other),
+    }
+}
+
+#[test]
+fn guest_is_not_logged_in() {
+    let server = MockServer::start().with_route("GET /", Reply::fixture("login.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    assert!(!is_logged_in(&client).unwrap());
+}
+
+#[test]
+fn current_session_reads_greeting() {
+    let server = MockServer::start().with_route("GET /", Reply::fixture("dashboard.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    assert_eq!(
+        current_session(&client).unwrap(),
+        Some(Session {
+            username: "reader".to_string()
+        })
+    );
+    assert!(is_logged_in(&client).unwrap());
+}
+
+#[test]
+fn logout_posts_delete_with_token() {
+    let server = MockServer::start()
+        .with_route(
+            "GET /token_dispenser.json",
+            Reply::fixture("token_dispenser.json"),
+        )
+        .with_route("POST /users/logout", Reply::redirect("/"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    logout(&client).unwrap();
+
+    let posted = server.received("POST /users/logout");
+    assert_eq!(posted.len(), 1);
+    let fields = form_fields(&posted[0].body);
+    assert_eq!(fields["_method"], "delete");
+    assert_eq!(fields["authenticity_token"], "dispensed+token/value==");
+}
+
+#[test]
+fn guest_cookie_is_not_a_session() {
+    let server = MockServer::start();
+    let path = cookie_file("guest");
+    save_jar(&server, &path, &["_otwarchive_session=guest; Path=/"]);
+    let jar = CookieJar::load(&path).unwrap();
+    assert!(!jar.has_session(&server.url("/")));
+    save_jar(&server, &path, &["user_credentials=1; Path=/"]);
+    let jar = CookieJar::load(&path).unwrap();
+    assert!(jar.has_session(&server.url("/")));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn resume_session_restores_saved_session() {
+    let server = MockServer::start().with_route("GET /", Reply::fixture("dashboard.html"));
+    let path = cookie_file("resume");
+    save_jar(&server, &path, &["user_credentials=1; Path=/"]);
+    let config = server.client_config().with_cookie_file(&path);
+    let client = Ao3Client::new(config).unwrap();
+
+    let session = resume_session(&client, LoginInfo::new("reader", "secret")).unwrap();
+    assert_eq!(session.username, "reader");
+    assert_eq!(server.received("GET /").len(), 1);
+    assert!(server.received("POST /users/login").is_empty());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn resume_session_logs_in_again_when_stale() {
+    let server = MockServer::start()
+        .with_route("GET /", Reply::fixture("login.html"))
+        .with_route(
+            "GET /token_dispenser.json",
+            Reply::fixture("token_dispenser.json"),
+        )
+        .with_route(
+            "POST /users/login",
+            Reply::redirect("/users/reader")
+                .with_header("Set-Cookie", "user_credentials=fresh; Path=/"),
+        )
+        .with_route("GET /users/reader", Reply::fixture("dashboard.html"));
+    let path = cookie_file("stale");
+    save_jar(&server, &path, &["user_credentials=stale; Path=/"]);
+    let config = server.client_config().with_cookie_file(&path);
+    let client = Ao3Client::new(config).unwrap();
+
+    let session = resume_session(&client, LoginInfo::new("reader", "secret")).unwrap();
+    assert_eq!(session.username, "reader");
+    assert_eq!(server.received("GET /").len(), 1);
+    assert_eq!(server.received("POST /users/login").len(), 1);
+
+    // the fresh session replaced the stale one on disk
+    let saved = fs::read_to_string(&path).unwrap();
+    assert!(saved.contains("fresh"), "{}", saved);
+    assert!(!saved.contains("stale"), "{}", saved);
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,320 @@
+use ao3_api_rs::Ao3ApiError;
+use ao3_api_rs::extraction::{Gate, extract_listing};
+use ao3_api_rs::networking::{
+    Ao3Client, Pages, WorkListing, get_init_page, get_page, get_work_page,
+};
+use ao3_api_rs::search::WorkSearch;
+use ao3_api_rs::testing::{MockServer, Reply, fixture};
+use std::time::Duration;
+
+#[test]
+fn get_page_follows_redirects() {
+    let server = MockServer::start()
+        .with_route(
+            "GET /works/1002",
+            Reply::redirect("/works/1002/chapters/2001"),
+        )
+        .with_route("GET /works/1002/chapters/2001", Reply::fixture("work.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let page = get_page(&server.url("/works/1002"), &client).unwrap();
+    assert_eq!(page.url().path(), "/works/1002/chapters/2001");
+    assert_eq!(page.redirects, vec![server.url("/works/1002")]);
+}
+
+#[test]
+fn get_page_reports_redirect_cycle() {
+    let server = MockServer::start()
+        .with_route("GET /works/1", Reply::redirect("/works/2"))
+        .with_route("GET /works/2", Reply::redirect("/works/1"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    match get_page(&server.url("/works/1"), &client).unwrap_err() {
+        Ao3ApiError::RedirectLoop(chain) => assert_eq!(
+            chain,
+            vec![
+                server.url("/works/1"),
+                server.url("/works/2"),
+                server.url("/works/1"),
+            ]
+        ),
+        other => panic!("expected RedirectLoop, got {:?}", other),
+    }
+    assert_eq!(server.received("GET /works/1").len(), 1);
+}
+
+#[test]
+fn get_page_stops_after_max_redirects() {
+    let server = MockServer::start()
+        .with_route("GET /r/1", Reply::redirect("/r/2"))
+        .with_route("GET /r/2", Reply::redirect("/r/3"))
+        .with_route("GET /r/3", Reply::redirect("/r/4"))
+        .with_route("GET /r/4", Reply::fixture("work.html"));
+    let config = server.client_config().with_max_redirects(2);
+    let client = Ao3Client::new(config).unwrap();
+    match get_page(&server.url("/r/1"), &client).unwrap_err() {
+        Ao3ApiError::RedirectLoop(chain) => {
+            let expected: Vec<String> = (1..=4).map(|n| server.url(&format!("/r/{}", n))).collect();
+            assert_eq!(chain, expected);
+        }
+        other => panic!("expected RedirectLoop, got {:?}", other),
+    }
+    assert!(server.received("GET /r/4").is_empty());
+}
+
+#[test]
+fn get_page_reports_login_redirect() {
+    let server = MockServer::start().with_route("GET /works/7", Reply::redirect("/users/login"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let err = get_page(&server.url("/works/7"), &client).unwrap_err();
+    assert!(
+        matches!(err, Ao3ApiError::LoginRequired { .. }),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn get_page_retries_unavailable() {
+    let server = MockServer::start()
+        .with_route("GET /works/1002", Reply::status(503))
+        .with_route("GET /works/1002", Reply::rate_limited(0))
+        .with_route("GET /works/1002", Reply::fixture("work.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let page = get_page(&server.url("/works/1002"), &client).unwrap();
+    assert_eq!(page.status(), 200);
+    assert_eq!(server.received("GET /works/1002").len(), 3);
+}
+
+#[test]
+fn get_page_gives_up_when_rate_limited() {
+    let server = MockServer::start().with_route("GET /works/1002", Reply::rate_limited(0));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let err = get_page(&server.url("/works/1002"), &client).unwrap_err();
+    match err {
+        Ao3ApiError::RateLimited { retry_after } => {
+            assert_eq!(retry_after, Some(Duration::ZERO))
+        }
+        other => panic!("expected RateLimited, got {:?}", other),
+    }
+    assert_eq!(server.received("GET /works/1002").len(), 3);
+}
+
+#[test]
+fn get_page_does_not_wait_for_long_retry_after() {
+    let server = MockServer::start()
+        .with_route("GET /works/1002", Reply::rate_limited(3600))
+        .with_route("GET /works/1002", Reply::fixture("work.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let err = get_page(&server.url("/works/1002"), &client).unwrap_err();
+    match err {
+        Ao3ApiError::RateLimited { retry_after } => {
+            assert_eq!(retry_after, Some(Duration::from_secs(3600)))
+        }
+        other => panic!("expected RateLimited, got {:?}", other),
+    }
+    assert_eq!(server.received("GET /works/1002").len(), 1);
+
+    let server = MockServer::start().with_route(
+        "GET /works/1002",
+        Reply::status(503).with_header("Retry-After", "Fri, 01 Jan 2100 00:00:00 GMT"),
+    );
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let err = get_page(&server.url("/works/1002"), &client).unwrap_err();
+    assert!(
+        matches!(err, Ao3ApiError::RateLimited { retry_after: Some(wait) } if wait > Duration::from_secs(3600)),
+        "{:?}",
+        err
+    );
+    assert_eq!(server.received("GET /works/1002").len(), 1);
+}
+
+#[test]
+fn get_page_fails_on_server_error() {
+    let server = MockServer::start().with_route("GET /works/1002", Reply::status(500));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let err = get_page(&server.url("/works/1002"), &client).unwrap_err();
+    assert!(
+        matches!(err, Ao3ApiError::Http { status, .. } if status == 500),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn get_work_page_accepts_adult_warning() {
+    let server = MockServer::start()
+        .with_route("GET /works/1002", Reply::fixture("gate_adult.html"))
+        .with_route("GET /works/1002", Reply::fixture("work.html"));
+    let config = server.client_config().with_view_adult(true);
+    let client = Ao3Client::new(config).unwrap();
+    let document = get_work_page("/works/1002", &client).unwrap();
+    assert!(document.html().contains("The Long Way Home"));
+    let requests = server.received("GET /works/1002");
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].path, "/works/1002?view_adult=true");
+}
+
+#[test]
+fn get_work_page_reports_adult_warning_without_view_adult() {
+    let server =
+        MockServer::start().with_route("GET /works/1002", Reply::fixture("gate_adult.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let err = get_work_page("/works/1002", &client).unwrap_err();
+    assert!(
+        matches!(err, Ao3ApiError::Gated(Gate::AdultContent)),
+        "{:?}",
+        err
+    );
+    assert_eq!(server.received("GET /works/1002").len(), 1);
+}
+
+#[test]
+fn get_work_page_reports_gates() {
+    let server = MockServer::start()
+        .with_route(
+            "GET /works/1",
+            Reply::redirect("/users/login?restricted=true"),
+        )
+        .with_route("GET /works/2", Reply::fixture("gate_hidden.html"))
+        .with_route("GET /works/3", Reply::fixture("gate_deleted.html"))
+        .with_route(
+            "GET /works/4",
+            Reply::status(404).with_body(&fixture("gate_404.html")),
+        );
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let cases = [
+        ("/works/1", Gate::LoginRequired),
+        ("/works/2", Gate::HiddenByAdmin),
+        ("/works/3", Gate::Deleted),
+        ("/works/4", Gate::NotFound),
+    ];
+    for (path, gate) in cases {
+        match get_work_page(path, &client) {
+            Err(Ao3ApiError::Gated(found)) => assert_eq!(found, gate, "{}", path),
+            other => panic!("{}: expected {:?}, got {:?}", path, gate, other.map(|_| ())),
+        }
+    }
+}
+
+#[test]
+fn get_init_page_reads_every_page() {
+    let server = MockServer::start()
+        .with_route("GET /works/search", Reply::fixture("search_page_1.html"))
+        .with_route("GET /works/search", Reply::fixture("search_page_2.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let document = get_init_page(
+        server.url("/works/search?commit=Search&work_search%5Bquery%5D=coffee"),
+        &client,
+    )
+    .unwrap();
+    let ids: Vec<String> = extract_listing(&document)
+        .into_iter()
+        .map(|fic| fic.unwrap().id)
+        .collect();
+    assert_eq!(ids, vec!["1001", "1002", "1003"]);
+
+    let requests = server.received("GET /works/search");
+    assert_eq!(requests.len(), 2);
+    assert!(requests[1].path.contains("page=2"), "{}", requests[1].path);
+}
+
+#[test]
+fn relative_urls_use_configured_base() {
+    let server = MockServer::start()
+        .with_route("GET /works/search", Reply::fixture("search_page_1.html"))
+        .with_route("GET /works/search", Reply::fixture("search_page_2.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let fics = WorkListing::new("/works/search?work_search%5Bquery%5D=coffee", &client)
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(fics[0].as_ref().unwrap().url, server.url("/works/1001"));
+}
+
+#[test]
+fn work_listing_for_tag() {
+    let server = MockServer::start().with_route(
+        "GET /tags/Hurt*s*Comfort/works",
+        Reply::fixture("search_page_2.html"),
+    );
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let mut listing = WorkListing::for_tag("Hurt/Comfort", &client);
+    assert!(!listing.next().unwrap().unwrap().is_empty());
+    assert!(listing.next().is_none());
+}
+
+#[test]
+fn pages_stop_after_an_error() {
+    let server = MockServer::start().with_route("GET /tags/Missing/works", Reply::status(404));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let mut pages = Pages::new("/tags/Missing/works", &client);
+    assert!(pages.next().unwrap().is_err());
+    assert!(pages.next().is_none());
+    assert_eq!(pages.cursor().next_page, 1);
+    assert!(!pages.cursor().finished);
+    assert_eq!(server.received("GET /tags/Missing/works").len(), 1);
+
+    // resuming from the saved cursor retries the page that failed
+    let mut resumed = Pages::from_cursor(pages.cursor().clone(), &client);
+    assert!(resumed.next().unwrap().is_err());
+    assert_eq!(server.received("GET /tags/Missing/works").len(), 2);
+}
+
+#[test]
+fn work_search_fetches_pages_lazily() {
+    let server = MockServer::start()
+        .with_route("GET /works/search", Reply::fixture("search_page_1.html"))
+        .with_route("GET /works/search", Reply::fixture("search_page_2.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let mut results = WorkSearch::new()
+        .with_query("coffee")
+        .listing(&client)
+        .unwrap();
+    let first = results.next().unwrap().unwrap();
+    assert_eq!(first.len(), 2);
+    assert_eq!(server.received("GET /works/search").len(), 1);
+
+    let second = results.next().unwrap().unwrap();
+    assert_eq!(second[0].as_ref().unwrap().id, "1003");
+    assert_eq!(server.received("GET /works/search").len(), 2);
+    assert!(results.next().is_none());
+}
+
+#[test]
+fn work_listing_reports_bad_blurbs_individually() {
+    let page = r#"<html><body><ol class="work index group">
+        <li class="work blurb group" role="article"><p>Nothing to see here</p></li>
+    </ol></body></html>"#;
+    let server = MockServer::start().with_route("GET /tags/Fluff/works", Reply::ok(page));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let fics = WorkListing::for_tag("Fluff", &client)
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(fics.len(), 1);
+    assert!(fics[0].is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn cookie_jar_is_saved_owner_only() {
+    use ao3_api_rs::networking::CookieJar;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    let path = std::env::temp_dir().join(format!("ao3-api-rs-cookies-{}.json", std::process::id()));
+    // an existing world readable file is tightened too
+    fs::write(&path, "[]").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+    CookieJar::new().save(&path).unwrap();
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    assert!(CookieJar::load(&path).is_ok());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn work_search_rejects_base_url_without_scheme() {
+    let search = WorkSearch::new().with_query("coffee");
+    assert!(search.url_with_base("archiveofourown.org").is_err());
+    assert!(search.url().is_ok());
+}
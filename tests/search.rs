@@ -0,0 +1,223 @@
+use ao3_api_rs::networking::Ao3Client;
+use ao3_api_rs::search::{
+    Category, DateRange, NumberRange, Rating, SortColumn, SortDirection, TimeUnit, Warning,
+    WorkSearch,
+};
+use ao3_api_rs::testing::{MockServer, Reply};
+
+/// Helper function to decode the query of a search URL into pairs
+fn pairs(search: &WorkSearch) -> Vec<(String, String)> {
+    search
+        .url()
+        .unwrap()
+        .query_pairs()
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect()
+}
+
+/// Helper function to find the value of `key` in the pairs
+fn value<'a>(pairs: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    pairs
+        .iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.as_str())
+}
+
+#[test]
+fn empty_search_only_sends_commit() {
+    let url = WorkSearch::new().url().unwrap();
+    assert_eq!(
+        url.as_str(),
+        "https://archiveofourown.org/works/search?commit=Search"
+    );
+}
+
+#[test]
+fn text_fields_are_form_encoded() {
+    let search = WorkSearch::new()
+        .with_query("tea & biscuits")
+        .with_title("A=B?")
+        .with_creators("some one");
+    let url = search.url().unwrap();
+    assert_eq!(
+        url.query(),
+        Some(
+            "commit=Search\
+             &work_search%5Bquery%5D=tea+%26+biscuits\
+             &work_search%5Btitle%5D=A%3DB%3F\
+             &work_search%5Bcreators%5D=some+one"
+        )
+    );
+}
+
+#[test]
+fn tag_names_are_joined_with_commas() {
+    let pairs = pairs(
+        &WorkSearch::new()
+            .with_fandom("Original Work")
+            .with_fandom("Sherlock (TV)")
+            .with_character("John Watson")
+            .with_relationship("John Watson/Sherlock Holmes")
+            .with_freeform("Hurt/Comfort")
+            .with_freeform("Fluff"),
+    );
+    assert_eq!(
+        value(&pairs, "work_search[fandom_names]"),
+        Some("Original Work,Sherlock (TV)")
+    );
+    assert_eq!(
+        value(&pairs, "work_search[character_names]"),
+        Some("John Watson")
+    );
+    assert_eq!(
+        value(&pairs, "work_search[relationship_names]"),
+        Some("John Watson/Sherlock Holmes")
+    );
+    assert_eq!(
+        value(&pairs, "work_search[freeform_names]"),
+        Some("Hurt/Comfort,Fluff")
+    );
+}
+
+#[test]
+fn ratings_warnings_and_categories_use_ao3_ids() {
+    let pairs = pairs(
+        &WorkSearch::new()
+            .with_rating(Rating::Explicit)
+            .with_warning(Warning::NoArchiveWarningsApply)
+            .with_warning(Warning::MajorCharacterDeath)
+            .with_category(Category::FemaleFemale)
+            .with_category(Category::Multi),
+    );
+    assert_eq!(value(&pairs, "work_search[rating_ids]"), Some("13"));
+    let warnings: Vec<&str> = pairs
+        .iter()
+        .filter(|(key, _)| key == "work_search[archive_warning_ids][]")
+        .map(|(_, value)| value.as_str())
+        .collect();
+    assert_eq!(warnings, vec!["16", "18"]);
+    let categories: Vec<&str> = pairs
+        .iter()
+        .filter(|(key, _)| key == "work_search[category_ids][]")
+        .map(|(_, value)| value.as_str())
+        .collect();
+    assert_eq!(categories, vec!["116", "2246"]);
+}
+
+#[test]
+fn flags_are_sent_as_ao3_expects() {
+    let set = pairs(
+        &WorkSearch::new()
+            .with_complete(true)
+            .with_crossover(false)
+            .with_single_chapter(true)
+            .with_language("en"),
+    );
+    assert_eq!(value(&set, "work_search[complete]"), Some("T"));
+    assert_eq!(value(&set, "work_search[crossover]"), Some("F"));
+    assert_eq!(value(&set, "work_search[single_chapter]"), Some("1"));
+    assert_eq!(value(&set, "work_search[language_id]"), Some("en"));
+
+    let unset = pairs(&WorkSearch::new().with_single_chapter(false));
+    assert_eq!(value(&unset, "work_search[single_chapter]"), None);
+    assert_eq!(value(&unset, "work_search[complete]"), None);
+}
+
+#[test]
+fn number_ranges() {
+    let pairs = pairs(
+        &WorkSearch::new()
+            .with_word_count(NumberRange::Between(1000, 5000))
+            .with_kudos(NumberRange::GreaterThan(100))
+            .with_hits(NumberRange::LessThan(50))
+            .with_bookmarks(NumberRange::Exact(3))
+            .with_comments(NumberRange::GreaterThan(0)),
+    );
+    assert_eq!(value(&pairs, "work_search[word_count]"), Some("1000-5000"));
+    assert_eq!(value(&pairs, "work_search[kudos_count]"), Some(">100"));
+    assert_eq!(value(&pairs, "work_search[hits]"), Some("<50"));
+    assert_eq!(value(&pairs, "work_search[bookmarks_count]"), Some("3"));
+    assert_eq!(value(&pairs, "work_search[comments_count]"), Some(">0"));
+
+    let url = WorkSearch::new()
+        .with_kudos(NumberRange::GreaterThan(100))
+        .url()
+        .unwrap();
+    assert!(
+        url.as_str()
+            .ends_with("work_search%5Bkudos_count%5D=%3E100"),
+        "{}",
+        url
+    );
+}
+
+#[test]
+fn revised_at_ranges() {
+    let cases = [
+        (DateRange::Before("2024-01-31".into()), "< 2024-01-31"),
+        (DateRange::After("2024-01-31".into()), "> 2024-01-31"),
+        (
+            DateRange::Between("2023-01-01".into(), "2023-12-31".into()),
+            "2023-01-01 - 2023-12-31",
+        ),
+        (DateRange::WithinLast(2, TimeUnit::Weeks), "< 2 weeks"),
+        (DateRange::OlderThan(1, TimeUnit::Years), "> 1 years"),
+    ];
+    for (range, expected) in cases {
+        let pairs = pairs(&WorkSearch::new().with_revised_at(range));
+        assert_eq!(value(&pairs, "work_search[revised_at]"), Some(expected));
+    }
+    let url = WorkSearch::new()
+        .with_revised_at(DateRange::WithinLast(2, TimeUnit::Weeks))
+        .url()
+        .unwrap();
+    assert!(
+        url.as_str()
+            .ends_with("work_search%5Brevised_at%5D=%3C+2+weeks"),
+        "{}",
+        url
+    );
+}
+
+#[test]
+fn sort_column_and_direction() {
+    let pairs = pairs(
+        &WorkSearch::new()
+            .with_sort_column(SortColumn::KudosCount)
+            .with_sort_direction(SortDirection::Ascending),
+    );
+    assert_eq!(
+        value(&pairs, "work_search[sort_column]"),
+        Some("kudos_count")
+    );
+    assert_eq!(value(&pairs, "work_search[sort_direction]"), Some("asc"));
+}
+
+#[test]
+fn execute_sends_the_search_and_collects_every_page() {
+    let server = MockServer::start()
+        .with_route("GET /works/search", Reply::fixture("search_page_1.html"))
+        .with_route("GET /works/search", Reply::fixture("search_page_2.html"));
+    let client = Ao3Client::new(server.client_config()).unwrap();
+    let search = WorkSearch::new()
+        .with_query("tea & biscuits")
+        .with_rating(Rating::GeneralAudiences);
+    let fics = search.execute(&client).unwrap();
+    let ids: Vec<&str> = fics.iter().map(|fic| fic.id.as_str()).collect();
+    assert_eq!(ids, vec!["1001", "1002", "1003"]);
+
+    let requests = server.received("GET /works/search");
+    assert_eq!(requests.len(), 2);
+    let expected = search.url_with_base(server.base_url()).unwrap();
+    assert_eq!(
+        requests[0].path,
+        format!("/works/search?{}&page=1", expected.query().unwrap())
+    );
+    assert!(
+        requests[0]
+            .path
+            .contains("work_search%5Bquery%5D=tea+%26+biscuits"),
+        "{}",
+        requests[0].path
+    );
+}
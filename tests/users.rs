@@ -0,0 +1,153 @@
+use ao3_api_rs::extraction::{
+    BookmarkedItem, Pseud, extract_user_bookmarks, extract_user_profile, extract_user_series,
+    extract_user_works,
+};
+use ao3_api_rs::testing::fixture;
+use scraper::Html;
+
+#[test]
+fn profile_page() {
+    let document = Html::parse_document(&fixture("profile.html"));
+    let profile = extract_user_profile(&document).unwrap();
+    assert_eq!(profile.user, "roadtripper");
+    assert_eq!(profile.user_id.as_deref(), Some("91002"));
+    assert_eq!(profile.joined.as_deref(), Some("2019-06-14"));
+    let pseuds: Vec<&str> = profile
+        .pseuds
+        .iter()
+        .map(|pseud| pseud.name.as_str())
+        .collect();
+    assert_eq!(pseuds, vec!["roadtripper", "Route 66"]);
+    assert!(
+        profile
+            .pseuds
+            .iter()
+            .all(|pseud| pseud.user == "roadtripper")
+    );
+    assert_eq!(
+        profile.bio.as_deref(),
+        Some("Writes about cars & the people in them.\n\nAsks welcome.")
+    );
+}
+
+#[test]
+fn works_map_co_authors_to_accounts() {
+    let document = Html::parse_document(&fixture("user_works.html"));
+    let works = extract_user_works(&document).unwrap();
+    let owner = works.owner.unwrap();
+    assert_eq!(owner.user, "roadtripper");
+    assert!(owner.is_default());
+    assert_eq!(works.info.total, Some(2));
+    assert_eq!(works.items.len(), 2);
+
+    let co_authored = works.items[1].as_ref().unwrap();
+    assert_eq!(co_authored.metadata.id, "1004");
+    assert_eq!(
+        co_authored.creators[1],
+        Pseud {
+            user: "birdwatcher".to_string(),
+            name: "Robin".to_string(),
+            url: "https://archiveofourown.org/users/birdwatcher/pseuds/Robin".to_string(),
+        }
+    );
+    assert_eq!(co_authored.metadata.authors[1], "Robin (birdwatcher)");
+}
+
+#[test]
+fn bad_blurb_does_not_fail_the_listing() {
+    let page = fixture("user_works.html").replacen(
+        "<ol class=\"work index group\">",
+        "<ol class=\"work index group\"><li class=\"work blurb group\" role=\"article\"><p>Broken</p></li>",
+        1,
+    );
+    let works = extract_user_works(&Html::parse_document(&page)).unwrap();
+    assert_eq!(works.items.len(), 3);
+    assert!(works.items[0].is_err());
+    assert!(works.items[1..].iter().all(Result::is_ok));
+}
+
+#[test]
+fn series_listing() {
+    let document = Html::parse_document(&fixture("user_series.html"));
+    let series = extract_user_series(&document).unwrap();
+    assert_eq!(series.info.total, Some(1));
+    let blurb = series.items[0].as_ref().unwrap();
+    assert_eq!(blurb.id, "77");
+    assert_eq!(blurb.title, "Highway Stories");
+    assert_eq!(blurb.creators[0].user, "roadtripper");
+    assert_eq!(blurb.fandoms, vec!["Original Work"]);
+    assert_eq!(blurb.updated.as_deref(), Some("17 Feb 2024"));
+    assert_eq!(blurb.description.as_deref(), Some("Stories from the road."));
+    assert_eq!(blurb.words, Some(24164));
+    assert_eq!(blurb.works_count, Some(2));
+    assert_eq!(blurb.bookmarks, Some(31));
+    assert_eq!(blurb.complete, Some(false));
+}
+
+#[test]
+fn bookmarks_keep_item_and_record_apart() {
+    let document = Html::parse_document(&fixture("user_bookmarks.html"));
+    let bookmarks = extract_user_bookmarks(&document).unwrap();
+    assert_eq!(bookmarks.owner.unwrap().user, "reader");
+    assert_eq!(bookmarks.info.total, Some(4));
+    assert_eq!(bookmarks.items.len(), 4);
+
+    let work = bookmarks.items[0].as_ref().unwrap();
+    let BookmarkedItem::Work(fic) = &work.item else {
+        panic!("expected a work, got {:?}", work.item);
+    };
+    assert_eq!(fic.metadata.id, "1002");
+    assert_eq!(fic.metadata.last_updated, "17 Feb 2024");
+    assert!(
+        fic.metadata
+            .tags
+            .values()
+            .flatten()
+            .all(|tag| tag != "favorites")
+    );
+    assert_eq!(work.record.id.as_deref(), Some("5001"));
+    assert_eq!(work.record.bookmarker.as_ref().unwrap().user, "reader");
+    assert_eq!(work.record.date.as_deref(), Some("20 Feb 2024"));
+    assert_eq!(work.record.tags, vec!["favorites", "to reread"]);
+    assert_eq!(work.record.collections, vec!["Road Fics"]);
+    assert_eq!(
+        work.record.notes.as_deref(),
+        Some("The motel chapter & the ending!")
+    );
+    assert!(work.record.rec);
+    assert!(!work.record.private);
+
+    let bookmark = bookmarks.items[1].as_ref().unwrap();
+    let BookmarkedItem::Series(series) = &bookmark.item else {
+        panic!("expected a series, got {:?}", bookmark.item);
+    };
+    assert_eq!(series.id, "77");
+    assert_eq!(series.works_count, Some(2));
+    assert!(!bookmark.record.rec);
+
+    let external = bookmarks.items[2].as_ref().unwrap();
+    let BookmarkedItem::External(story) = &external.item else {
+        panic!("expected an external work, got {:?}", external.item);
+    };
+    assert_eq!(story.id, "555");
+    assert_eq!(story.title, "Letters by Lamplight");
+    assert_eq!(story.creators, vec!["Anonymous Scribe", "A. N. Other"]);
+    assert_eq!(story.summary.as_deref(), Some("An exchange of letters."));
+    assert_eq!(
+        story.tags.get("freeforms"),
+        Some(&vec!["Epistolary".to_string()])
+    );
+    assert!(external.record.private);
+
+    let deleted = bookmarks.items[3].as_ref().unwrap();
+    assert!(matches!(
+        &deleted.item,
+        BookmarkedItem::Unavailable(Some(message)) if message == "This has been deleted, sorry!"
+    ));
+    assert_eq!(deleted.record.id.as_deref(), Some("5004"));
+    assert_eq!(deleted.record.bookmarker.as_ref().unwrap().user, "reader");
+    assert_eq!(
+        deleted.record.notes.as_deref(),
+        Some("Gone but not forgotten.")
+    );
+}